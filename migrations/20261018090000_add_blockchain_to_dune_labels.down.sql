@@ -0,0 +1,2 @@
+ALTER TABLE dune_labels ALTER COLUMN address TYPE CHAR(42);
+ALTER TABLE dune_labels DROP COLUMN blockchain;
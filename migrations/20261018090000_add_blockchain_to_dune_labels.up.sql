@@ -0,0 +1,5 @@
+-- Every label crawled so far came from Ethereum
+ALTER TABLE dune_labels ADD COLUMN blockchain TEXT NOT NULL DEFAULT 'ethereum';
+ALTER TABLE dune_labels ALTER COLUMN blockchain DROP DEFAULT;
+-- Addresses of non-EVM chains are not 42 characters long
+ALTER TABLE dune_labels ALTER COLUMN address TYPE TEXT;
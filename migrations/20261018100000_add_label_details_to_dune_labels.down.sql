@@ -0,0 +1,7 @@
+ALTER TABLE dune_labels
+    DROP COLUMN category,
+    DROP COLUMN contributor,
+    DROP COLUMN source,
+    DROP COLUMN created_at,
+    DROP COLUMN updated_at,
+    DROP COLUMN model_name;
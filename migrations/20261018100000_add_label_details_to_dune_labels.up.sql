@@ -0,0 +1,8 @@
+-- What the Spellbook labels tables tell about a label, NULL for labels of the legacy engine
+ALTER TABLE dune_labels
+    ADD COLUMN category    TEXT,
+    ADD COLUMN contributor TEXT,
+    ADD COLUMN source      TEXT,
+    ADD COLUMN created_at  TIMESTAMPTZ,
+    ADD COLUMN updated_at  TIMESTAMPTZ,
+    ADD COLUMN model_name  TEXT;
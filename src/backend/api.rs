@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use log::{info, warn};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{DuneBackend, JobStatus};
+use crate::{configuration::QueryCleanup, domain::*};
+
+const API_KEY_HEADER: &str = "X-Dune-API-Key";
+
+#[derive(Serialize)]
+struct CreateQueryRequest<'a> {
+    name: &'static str,
+    query_sql: &'a str,
+    parameters: &'a [Parameter],
+    is_private: bool,
+    tags: Vec<&'static str>,
+}
+
+#[derive(Deserialize)]
+struct QueryIdResponse {
+    query_id: i32,
+}
+
+#[derive(Serialize)]
+struct ExecuteRequest<'a> {
+    query_parameters: HashMap<&'a str, &'a str>,
+}
+
+#[derive(Deserialize)]
+struct ExecuteResponse {
+    execution_id: String,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    state: String,
+    #[serde(default)]
+    queue_position: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CancelResponse {
+    success: bool,
+}
+
+#[derive(Deserialize)]
+struct ResultMetadata {
+    #[serde(default)]
+    column_names: Vec<String>,
+    #[serde(default)]
+    execution_time_millis: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct ExecutionResult<T> {
+    rows: Vec<T>,
+    metadata: Option<ResultMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct ResultsResponse<T> {
+    state: String,
+    #[serde(default)]
+    result: Option<ExecutionResult<T>>,
+    #[serde(default)]
+    error: Option<QueryError>,
+    #[serde(default)]
+    next_uri: Option<String>,
+}
+
+/// Whether an execution `state` is still queued or running.
+fn is_running(state: &str) -> bool {
+    matches!(state, "QUERY_STATE_PENDING" | "QUERY_STATE_EXECUTING")
+}
+
+/// The public Dune REST API, signed in with an API key.
+pub(crate) struct ApiBackend {
+    client: Client,
+    api_url: String,
+    api_key: Secret<String>,
+}
+
+impl ApiBackend {
+    pub(crate) fn new(api_key: Secret<String>, api_url: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            client: Client::builder().build()?,
+            api_url: api_url.trim_end_matches('/').to_owned(),
+            api_key,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_url, path)
+    }
+
+    /// Check that Dune takes the API key by asking for the status of an execution that does not
+    /// exist: Dune answers 401 or 403 to a wrong or revoked key, and a client error about the
+    /// execution otherwise.
+    pub(crate) async fn check_key(&self) -> Result<(), DuneError> {
+        let response = self
+            .client
+            .get(self.url("/execution/00000000000000000000000000/status"))
+            .header(API_KEY_HEADER, self.api_key.expose_secret())
+            .send()
+            .await?;
+
+        match read_response(response).await? {
+            (status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN), body) => {
+                Err(DuneError::Status { status, body })
+            }
+            (status, body) if status.is_server_error() => Err(DuneError::Status { status, body }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The rows of the latest result of the saved query `query_id`, without running it.
+    pub(crate) async fn latest_results<T>(&self, query_id: i32) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch_pages(
+            self.url(&format!("/query/{}/results", query_id)),
+            &format!("latest of query {}", query_id),
+        )
+        .await
+    }
+
+    /// The rows of the result at `url` and of every page it points at.
+    async fn fetch_pages<T>(&self, mut url: String, job_id: &str) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut rows = Vec::new();
+
+        // Large results come in pages, each pointing at the next one.
+        loop {
+            let response: ResultsResponse<T> = self.send(self.client.get(&url)).await?;
+
+            if let Some(error) = response.error {
+                return Err(DuneError::Sql {
+                    job_id: job_id.to_owned(),
+                    error,
+                });
+            }
+
+            let result = match response.result {
+                Some(result) if response.state == "QUERY_STATE_COMPLETED" => result,
+                _ => {
+                    return Err(DuneError::QueryFailed {
+                        job_id: job_id.to_owned(),
+                        message: format!("the job has no result, {}", response.state),
+                    })
+                }
+            };
+
+            if rows.is_empty() {
+                if let Some(metadata) = result.metadata {
+                    info!(
+                        "job {} ran {}s, columns: {}",
+                        job_id,
+                        metadata.execution_time_millis.unwrap_or_default() as f64 / 1000.0,
+                        metadata.column_names.join(", ")
+                    );
+                }
+            }
+            rows.extend(result.rows);
+
+            match response.next_uri {
+                Some(next_uri) => url = next_uri,
+                None => return Ok(rows),
+            }
+        }
+    }
+
+    /// Send `request` with the API key and decode the JSON body of a successful response.
+    async fn send<T>(&self, request: RequestBuilder) -> Result<T, DuneError>
+    where
+        T: DeserializeOwned,
+    {
+        let response = request
+            .header(API_KEY_HEADER, self.api_key.expose_secret())
+            .send()
+            .await?;
+        let (status, body) = read_response(response).await?;
+
+        if !status.is_success() {
+            return Err(DuneError::Status { status, body });
+        }
+
+        serde_json::from_str(&body).map_err(|source| DuneError::Decode { source, body })
+    }
+}
+
+impl DuneBackend for ApiBackend {
+    async fn save_query(
+        &self,
+        name: &'static str,
+        sql: &str,
+        parameters: &[Parameter],
+    ) -> Result<i32, DuneError> {
+        let request = CreateQueryRequest {
+            name,
+            query_sql: sql,
+            parameters,
+            is_private: false,
+            tags: vec![CRAWLER_QUERY_TAG],
+        };
+        let saved: QueryIdResponse = self
+            .send(self.client.post(self.url("/query")).json(&request))
+            .await?;
+
+        info!(
+            "saved query {} with parameters {}",
+            saved.query_id,
+            parameters
+                .iter()
+                .map(Parameter::key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Ok(saved.query_id)
+    }
+
+    async fn execute(&self, query_id: i32, parameters: &[Parameter]) -> Result<String, DuneError> {
+        let request = ExecuteRequest {
+            query_parameters: parameters
+                .iter()
+                .map(|parameter| (parameter.key(), parameter.value()))
+                .collect(),
+        };
+
+        self.send(
+            self.client
+                .post(self.url(&format!("/query/{}/execute", query_id)))
+                .json(&request),
+        )
+        .await
+        .map(|response: ExecuteResponse| response.execution_id)
+    }
+
+    async fn status(&self, job_id: &str) -> Result<JobStatus, DuneError> {
+        let response: StatusResponse = self
+            .send(
+                self.client
+                    .get(self.url(&format!("/execution/{}/status", job_id))),
+            )
+            .await?;
+
+        Ok(match is_running(&response.state) {
+            true => JobStatus::Running {
+                position: response.queue_position,
+                locked_until: None,
+            },
+            false => JobStatus::Finished,
+        })
+    }
+
+    async fn cancel(&self, job_id: &str) -> Result<bool, DuneError> {
+        self.send(
+            self.client
+                .post(self.url(&format!("/execution/{}/cancel", job_id))),
+        )
+        .await
+        .map(|response: CancelResponse| response.success)
+    }
+
+    async fn fetch<T>(&self, job_id: &str) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned + Send,
+    {
+        self.fetch_pages(self.url(&format!("/execution/{}/results", job_id)), job_id)
+            .await
+    }
+
+    async fn discard_query(&self, query_id: i32, cleanup: QueryCleanup) -> Result<bool, DuneError> {
+        match cleanup {
+            QueryCleanup::Archive => {}
+            // The API has no way to delete a query, archiving it is as close as it gets.
+            QueryCleanup::Delete => warn!("the API cannot delete queries, archiving instead"),
+            QueryCleanup::Keep => return Ok(true),
+        }
+
+        self.send(
+            self.client
+                .post(self.url(&format!("/query/{}/archive", query_id))),
+        )
+        .await
+        .map(|_: Value| true)
+    }
+
+    async fn crawler_queries(&self, _: bool) -> Result<Vec<CrawlerQuery>, DuneError> {
+        Err(DuneError::Unsupported {
+            backend: "api",
+            action: "list saved queries",
+        })
+    }
+}
+
+#[test]
+fn deserialize_results_response_should_ok() {
+    let json = r#"
+    {
+        "execution_id": "01HKZJ2683PHF9Q9PHHQ8FW4Q1",
+        "query_id": 3493826,
+        "is_execution_finished": true,
+        "state": "QUERY_STATE_COMPLETED",
+        "submitted_at": "2024-12-20T11:04:18.724658Z",
+        "result": {
+            "rows": [{"address": "\\x00ff", "label_name": "x", "label_type": "dao"}],
+            "metadata": {
+                "column_names": ["address", "label_name", "label_type"],
+                "row_count": 1,
+                "execution_time_millis": 1234
+            }
+        },
+        "next_uri": "https://api.dune.com/api/v1/execution/01HKZJ2683PHF9Q9PHHQ8FW4Q1/results?offset=1&limit=1"
+    }
+    "#;
+
+    let response = serde_json::from_str::<ResultsResponse<Value>>(json).unwrap();
+    assert_eq!(response.result.unwrap().rows.len(), 1);
+    assert!(response.next_uri.is_some());
+
+    let json = r#"
+    {
+        "execution_id": "01HKZJ2683PHF9Q9PHHQ8FW4Q1",
+        "state": "QUERY_STATE_FAILED",
+        "error": {
+            "type": "FAILED_TYPE_EXECUTION_FAILED",
+            "message": "line 1:8: Column 'foo' cannot be resolved",
+            "metadata": {"line": 1, "column": 8}
+        }
+    }
+    "#;
+
+    let response = serde_json::from_str::<ResultsResponse<Value>>(json).unwrap();
+    assert!(response.result.is_none());
+    assert_eq!(
+        response.error.unwrap().to_string(),
+        r#"FAILED_TYPE_EXECUTION_FAILED error, line 1:8: Column 'foo' cannot be resolved ({"column":8,"line":1})"#
+    );
+}
+
+#[test]
+fn execution_state_should_tell_running_jobs() {
+    assert!(is_running("QUERY_STATE_PENDING"));
+    assert!(is_running("QUERY_STATE_EXECUTING"));
+    assert!(!is_running("QUERY_STATE_COMPLETED"));
+    assert!(!is_running("QUERY_STATE_CANCELLED"));
+}
@@ -0,0 +1,150 @@
+mod api;
+mod web;
+
+use std::future::Future;
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+
+use crate::{configuration::*, domain::*};
+
+pub(crate) use api::*;
+pub(crate) use web::*;
+
+/// Where a job is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JobStatus {
+    /// Queued or running, at `position` in the queue while queued
+    Running {
+        position: Option<i64>,
+        locked_until: Option<DateTime<Utc>>,
+    },
+    /// Done, failed or cancelled: what is left to do is fetching its rows or its error
+    Finished,
+}
+
+/// A Dune API able to save a query, run it as a job and hand out the rows of the job.
+///
+/// Every method sends a single request, retrying is up to the caller.
+pub(crate) trait DuneBackend: Send + Sync {
+    /// Save `sql` as a query named `name`, its `{{key}}` placeholders defaulting to `parameters`.
+    fn save_query(
+        &self,
+        name: &'static str,
+        sql: &str,
+        parameters: &[Parameter],
+    ) -> impl Future<Output = Result<i32, DuneError>> + Send;
+
+    /// Start a job running the saved query `query_id`.
+    fn execute(
+        &self,
+        query_id: i32,
+        parameters: &[Parameter],
+    ) -> impl Future<Output = Result<String, DuneError>> + Send;
+
+    fn status(&self, job_id: &str) -> impl Future<Output = Result<JobStatus, DuneError>> + Send;
+
+    /// Cancel the job, `false` when it already finished.
+    fn cancel(&self, job_id: &str) -> impl Future<Output = Result<bool, DuneError>> + Send;
+
+    /// The rows of the finished job, or the SQL error it failed with.
+    fn fetch<T>(&self, job_id: &str) -> impl Future<Output = Result<Vec<T>, DuneError>> + Send
+    where
+        T: DeserializeOwned + Send;
+
+    /// Archive or delete the saved query `query_id`, `false` when it is already gone.
+    fn discard_query(
+        &self,
+        query_id: i32,
+        cleanup: QueryCleanup,
+    ) -> impl Future<Output = Result<bool, DuneError>> + Send;
+
+    /// The queries crawls saved, archived ones too when `include_archived`.
+    fn crawler_queries(
+        &self,
+        include_archived: bool,
+    ) -> impl Future<Output = Result<Vec<CrawlerQuery>, DuneError>> + Send;
+}
+
+/// The backend `dune.backend` selects.
+pub(crate) enum Backend {
+    Web(Box<WebBackend>),
+    Api(ApiBackend),
+}
+
+impl Backend {
+    pub(crate) fn from_settings(settings: &Settings) -> Result<Self, anyhow::Error> {
+        let dune = settings.dune();
+
+        Ok(match dune.backend() {
+            BackendKind::Web => Self::Web(Box::new(WebBackend::new(settings.application(), dune)?)),
+            BackendKind::Api if dune.engine() != Engine::DuneSql => {
+                bail!("the api backend only runs DuneSQL, set `dune.engine` to `dune_sql`")
+            }
+            BackendKind::Api => Self::Api(ApiBackend::new(dune.api_key()?, dune.api_url())?),
+        })
+    }
+}
+
+impl DuneBackend for Backend {
+    async fn save_query(
+        &self,
+        name: &'static str,
+        sql: &str,
+        parameters: &[Parameter],
+    ) -> Result<i32, DuneError> {
+        match self {
+            Self::Web(web) => web.save_query(name, sql, parameters).await,
+            Self::Api(api) => api.save_query(name, sql, parameters).await,
+        }
+    }
+
+    async fn execute(&self, query_id: i32, parameters: &[Parameter]) -> Result<String, DuneError> {
+        match self {
+            Self::Web(web) => web.execute(query_id, parameters).await,
+            Self::Api(api) => api.execute(query_id, parameters).await,
+        }
+    }
+
+    async fn status(&self, job_id: &str) -> Result<JobStatus, DuneError> {
+        match self {
+            Self::Web(web) => web.status(job_id).await,
+            Self::Api(api) => api.status(job_id).await,
+        }
+    }
+
+    async fn cancel(&self, job_id: &str) -> Result<bool, DuneError> {
+        match self {
+            Self::Web(web) => web.cancel(job_id).await,
+            Self::Api(api) => api.cancel(job_id).await,
+        }
+    }
+
+    async fn fetch<T>(&self, job_id: &str) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned + Send,
+    {
+        match self {
+            Self::Web(web) => web.fetch(job_id).await,
+            Self::Api(api) => api.fetch(job_id).await,
+        }
+    }
+
+    async fn discard_query(&self, query_id: i32, cleanup: QueryCleanup) -> Result<bool, DuneError> {
+        match self {
+            Self::Web(web) => web.discard_query(query_id, cleanup).await,
+            Self::Api(api) => api.discard_query(query_id, cleanup).await,
+        }
+    }
+
+    async fn crawler_queries(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<CrawlerQuery>, DuneError> {
+        match self {
+            Self::Web(web) => web.crawler_queries(include_archived).await,
+            Self::Api(api) => api.crawler_queries(include_archived).await,
+        }
+    }
+}
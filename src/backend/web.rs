@@ -0,0 +1,144 @@
+use log::info;
+use serde::de::DeserializeOwned;
+
+use super::{DuneBackend, JobStatus};
+use crate::{configuration::*, domain::*, session::SessionManager};
+
+/// The GraphQL API of the Dune web app, signed in with a browser session.
+pub(crate) struct WebBackend {
+    session: SessionManager,
+    graphql_url: String,
+    userid: i32,
+    engine: Engine,
+}
+
+impl WebBackend {
+    pub(crate) fn new(
+        application: &ApplicationSettings,
+        dune: &DuneSettings,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            session: SessionManager::new(application, dune)?,
+            graphql_url: dune.graphql_url().to_owned(),
+            userid: application.userid(),
+            engine: dune.engine(),
+        })
+    }
+
+    async fn send<O>(&self, operation: &O) -> Result<O::Response, DuneError>
+    where
+        O: GraphQLOperation,
+    {
+        self.session.execute(&self.graphql_url, operation).await
+    }
+}
+
+impl DuneBackend for WebBackend {
+    async fn save_query(
+        &self,
+        name: &'static str,
+        sql: &str,
+        parameters: &[Parameter],
+    ) -> Result<i32, DuneError> {
+        let saved = self
+            .send(&UpsertQuery::new(
+                self.userid,
+                self.userid,
+                self.engine.dataset_id(),
+                name,
+                sql.to_owned(),
+                parameters.to_vec(),
+            ))
+            .await?;
+
+        info!(
+            "saved query {} with parameters {}",
+            saved.query_id(),
+            saved
+                .parameters()
+                .iter()
+                .map(Parameter::key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Ok(saved.query_id())
+    }
+
+    async fn execute(&self, query_id: i32, parameters: &[Parameter]) -> Result<String, DuneError> {
+        self.send(&ExecuteQuery::new(query_id, parameters.to_vec()))
+            .await
+            .map(ExecuteQueryResponse::job_id)
+    }
+
+    async fn status(&self, job_id: &str) -> Result<JobStatus, DuneError> {
+        let response = self.send(&GetQueuePosition::new(job_id.to_owned())).await?;
+
+        Ok(match response.is_executing() {
+            true => JobStatus::Running {
+                position: response.position(),
+                locked_until: response.locked_until(),
+            },
+            false => JobStatus::Finished,
+        })
+    }
+
+    async fn cancel(&self, job_id: &str) -> Result<bool, DuneError> {
+        self.send(&CancelQuery::new(job_id.to_owned()))
+            .await
+            .map(|response| response.job_id().is_some())
+    }
+
+    async fn fetch<T>(&self, job_id: &str) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let mut response = self
+            .send(&FindResultDataByJobId::new(job_id.to_owned()))
+            .await?;
+
+        if let Some(error) = response.take_query_error() {
+            return Err(DuneError::Sql {
+                job_id: job_id.to_owned(),
+                error,
+            });
+        }
+
+        match response.query_result() {
+            Some(result) => info!(
+                "job {} ran {}s, generated at {}, columns: {}",
+                job_id,
+                result.runtime().unwrap_or_default(),
+                result.generated_at(),
+                result.columns().join(", ")
+            ),
+            None => {
+                return Err(DuneError::QueryFailed {
+                    job_id: job_id.to_owned(),
+                    message: String::from("the job has no result"),
+                })
+            }
+        }
+
+        Ok(response.data())
+    }
+
+    async fn discard_query(&self, query_id: i32, cleanup: QueryCleanup) -> Result<bool, DuneError> {
+        let discarded = match cleanup {
+            QueryCleanup::Archive => self.send(&ArchiveQuery::new(query_id)).await?,
+            QueryCleanup::Delete => self.send(&DeleteQuery::new(query_id)).await?,
+            QueryCleanup::Keep => return Ok(true),
+        };
+
+        Ok(discarded.query_id().is_some())
+    }
+
+    async fn crawler_queries(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<CrawlerQuery>, DuneError> {
+        self.send(&FindCrawlerQueries::new(self.userid, include_archived))
+            .await
+            .map(FindCrawlerQueriesResponse::queries)
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::bail;
+use rayon::prelude::*;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::configuration::{CatalogSettings, DuneSettings, Engine};
+
+mod rules;
+mod source;
+
+pub(crate) use rules::*;
+pub(crate) use source::load;
+
+/// The chain of catalogs without a `blockchain` column, and the only one the legacy engine has.
+pub(crate) const ETHEREUM: &str = "ethereum";
+
+fn default_blockchain() -> String {
+    String::from(ETHEREUM)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LabelData {
+    #[serde(default = "default_blockchain")]
+    blockchain: String,
+    label_name: String,
+    label_type: String,
+    amount: usize,
+}
+
+impl LabelData {
+    pub(crate) fn blockchain(&self) -> &str {
+        &self.blockchain
+    }
+
+    pub(crate) fn label_name(&self) -> &str {
+        &self.label_name
+    }
+
+    pub(crate) fn label_type(&self) -> &str {
+        &self.label_type
+    }
+
+    pub(crate) fn amount(&self) -> usize {
+        self.amount
+    }
+
+    pub(crate) fn into_parts(self) -> (String, String, String, usize) {
+        (
+            self.blockchain,
+            self.label_type,
+            self.label_name,
+            self.amount,
+        )
+    }
+}
+
+/// Fetch the label categories of the configured chains accepted by the catalog rules.
+pub(crate) async fn fetch(
+    client: &Client,
+    dune: &DuneSettings,
+    settings: &CatalogSettings,
+) -> Result<Vec<LabelData>, anyhow::Error> {
+    let categories = load(client, dune, settings.source())
+        .await?
+        .into_par_iter()
+        .filter(|data| settings.evaluate(data).is_accepted())
+        .collect::<Vec<_>>();
+
+    if dune.engine() == Engine::Postgres {
+        if let Some(data) = categories.iter().find(|data| data.blockchain() != ETHEREUM) {
+            bail!(
+                "the postgres engine only has {} labels, not {}, pick another `dune.engine`",
+                ETHEREUM,
+                data.blockchain()
+            );
+        }
+    }
+
+    Ok(categories)
+}
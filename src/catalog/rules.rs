@@ -0,0 +1,330 @@
+use std::fmt::{Display, Formatter};
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::LabelData;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RawMatcher {
+    Exact(String),
+    Glob(String),
+    Regex(String),
+}
+
+/// How a rule compares a `blockchain`, a `label_type` or a `label_name`.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "RawMatcher")]
+pub(crate) enum Matcher {
+    Exact(String),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl TryFrom<RawMatcher> for Matcher {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawMatcher) -> Result<Self, Self::Error> {
+        Ok(match raw {
+            RawMatcher::Exact(value) => Matcher::Exact(value),
+            RawMatcher::Glob(pattern) => Matcher::Glob(Glob::new(&pattern)?.compile_matcher()),
+            RawMatcher::Regex(pattern) => Matcher::Regex(Regex::new(&pattern)?),
+        })
+    }
+}
+
+impl Matcher {
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            Matcher::Exact(expected) => expected == value,
+            Matcher::Glob(glob) => glob.is_match(value),
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+impl Display for Matcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Matcher::Exact(value) => write!(f, "exact {:?}", value),
+            Matcher::Glob(glob) => write!(f, "glob {:?}", glob.glob().glob()),
+            Matcher::Regex(regex) => write!(f, "regex {:?}", regex.as_str()),
+        }
+    }
+}
+
+/// A rule matches a category when every matcher it sets matches.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Rule {
+    blockchain: Option<Matcher>,
+    label_type: Option<Matcher>,
+    label_name: Option<Matcher>,
+}
+
+impl Rule {
+    /// The matchers the rule sets, by the field they compare.
+    fn matchers(&self) -> impl Iterator<Item = (&'static str, &Matcher)> {
+        [
+            ("blockchain", &self.blockchain),
+            ("label_type", &self.label_type),
+            ("label_name", &self.label_name),
+        ]
+        .into_iter()
+        .filter_map(|(field, matcher)| matcher.as_ref().map(|matcher| (field, matcher)))
+    }
+
+    fn is_match(&self, data: &LabelData) -> bool {
+        [
+            (&self.blockchain, data.blockchain()),
+            (&self.label_type, data.label_type()),
+            (&self.label_name, data.label_name()),
+        ]
+        .into_iter()
+        .all(|(matcher, value)| {
+            matcher
+                .as_ref()
+                .is_none_or(|matcher| matcher.is_match(value))
+        })
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let matchers = self
+            .matchers()
+            .map(|(field, matcher)| format!("{} {}", field, matcher))
+            .collect::<Vec<_>>();
+
+        match matchers.is_empty() {
+            true => write!(f, "any"),
+            false => write!(f, "{}", matchers.join(" and ")),
+        }
+    }
+}
+
+/// Decides which label categories get crawled.
+///
+/// A category is rejected when its amount is out of `min_amount..=max_amount` or any `exclude` rule
+/// matches it. Otherwise it is accepted when `include` is empty or any `include` rule matches it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct CatalogRules {
+    min_amount: Option<usize>,
+    max_amount: Option<usize>,
+    include: Vec<Rule>,
+    exclude: Vec<Rule>,
+}
+
+/// Why a category was accepted or rejected.
+#[derive(Debug)]
+pub(crate) enum Verdict<'a> {
+    Accepted(Reason<'a>),
+    Rejected(Reason<'a>),
+}
+
+#[derive(Debug)]
+pub(crate) enum Reason<'a> {
+    Default,
+    MinAmount(usize),
+    MaxAmount(usize),
+    Include(usize, &'a Rule),
+    Exclude(usize, &'a Rule),
+    NoIncludeMatched,
+    /// The category is of a chain missing from `catalog.chains`
+    ChainNotConfigured,
+}
+
+impl Verdict<'_> {
+    pub(crate) fn is_accepted(&self) -> bool {
+        matches!(self, Verdict::Accepted(_))
+    }
+}
+
+impl Display for Verdict<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Accepted(reason) => write!(f, "accepted by {}", reason),
+            Verdict::Rejected(reason) => write!(f, "rejected by {}", reason),
+        }
+    }
+}
+
+impl Display for Reason<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::Default => write!(f, "default"),
+            Reason::MinAmount(min) => write!(f, "min_amount {}", min),
+            Reason::MaxAmount(max) => write!(f, "max_amount {}", max),
+            Reason::Include(index, rule) => write!(f, "include[{}] {}", index, rule),
+            Reason::Exclude(index, rule) => write!(f, "exclude[{}] {}", index, rule),
+            Reason::NoIncludeMatched => write!(f, "no include rule"),
+            Reason::ChainNotConfigured => write!(f, "chains"),
+        }
+    }
+}
+
+impl CatalogRules {
+    pub(crate) fn evaluate(&self, data: &LabelData) -> Verdict<'_> {
+        if let Some(min) = self.min_amount.filter(|min| data.amount() < *min) {
+            return Verdict::Rejected(Reason::MinAmount(min));
+        }
+
+        if let Some(max) = self.max_amount.filter(|max| data.amount() > *max) {
+            return Verdict::Rejected(Reason::MaxAmount(max));
+        }
+
+        if let Some((index, rule)) = self
+            .exclude
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.is_match(data))
+        {
+            return Verdict::Rejected(Reason::Exclude(index, rule));
+        }
+
+        if self.include.is_empty() {
+            return Verdict::Accepted(Reason::Default);
+        }
+
+        self.include
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.is_match(data))
+            .map(|(index, rule)| Verdict::Accepted(Reason::Include(index, rule)))
+            .unwrap_or(Verdict::Rejected(Reason::NoIncludeMatched))
+    }
+}
+
+#[cfg(test)]
+fn from_yaml<T: serde::de::DeserializeOwned>(yaml: &str) -> T {
+    use config::{Config, File, FileFormat};
+
+    Config::builder()
+        .add_source(File::from_str(yaml, FileFormat::Yaml))
+        .build()
+        .and_then(|config| config.try_deserialize())
+        .unwrap()
+}
+
+#[cfg(test)]
+fn label_data(label_type: &str, label_name: &str, amount: usize) -> LabelData {
+    chain_label_data("ethereum", label_type, label_name, amount)
+}
+
+#[cfg(test)]
+fn chain_label_data(
+    blockchain: &str,
+    label_type: &str,
+    label_name: &str,
+    amount: usize,
+) -> LabelData {
+    serde_json::from_value(serde_json::json!({
+        "blockchain": blockchain,
+        "label_type": label_type,
+        "label_name": label_name,
+        "amount": amount,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn catalog_rules_should_reject_by_amount_and_exclude() {
+    let rules: CatalogRules = from_yaml(
+        r#"
+        min_amount: 2
+        exclude:
+          - label_type:
+              glob: "ens*"
+          - label_type:
+              regex: "contract"
+        "#,
+    );
+
+    assert_eq!(
+        rules.evaluate(&label_data("dao", "x", 1)).to_string(),
+        "rejected by min_amount 2"
+    );
+    assert_eq!(
+        rules.evaluate(&label_data("ens name", "x", 10)).to_string(),
+        r#"rejected by exclude[0] label_type glob "ens*""#
+    );
+    assert_eq!(
+        rules
+            .evaluate(&label_data("contract deployer", "x", 10))
+            .to_string(),
+        r#"rejected by exclude[1] label_type regex "contract""#
+    );
+    assert!(rules.evaluate(&label_data("dao", "x", 10)).is_accepted());
+}
+
+#[test]
+fn catalog_rules_should_require_an_include_match() {
+    let rules: CatalogRules = from_yaml(
+        r#"
+        max_amount: 100
+        include:
+          - label_type:
+              exact: "dao"
+            label_name:
+              glob: "Ol' *"
+        "#,
+    );
+
+    assert_eq!(
+        rules
+            .evaluate(&label_data("dao", "Ol' Bob", 10))
+            .to_string(),
+        r#"accepted by include[0] label_type exact "dao" and label_name glob "Ol' *""#
+    );
+    assert!(!rules.evaluate(&label_data("dao", "Bob", 10)).is_accepted());
+    assert!(!rules
+        .evaluate(&label_data("dao", "Ol' Bob", 1000))
+        .is_accepted());
+}
+
+#[test]
+fn catalog_rules_should_match_the_blockchain() {
+    let rules: CatalogRules = from_yaml(
+        r#"
+        exclude:
+          - blockchain:
+              exact: "bnb"
+            label_type:
+              exact: "dao"
+        "#,
+    );
+
+    assert_eq!(
+        rules
+            .evaluate(&chain_label_data("bnb", "dao", "x", 10))
+            .to_string(),
+        r#"rejected by exclude[0] blockchain exact "bnb" and label_type exact "dao""#
+    );
+    assert!(rules
+        .evaluate(&chain_label_data("polygon", "dao", "x", 10))
+        .is_accepted());
+}
+
+#[test]
+fn catalog_settings_should_reject_chains_not_configured() {
+    let settings: crate::configuration::CatalogSettings = from_yaml(
+        r#"
+        source:
+          kind: file
+          value: "catalog.csv"
+        chains: ["ethereum", "polygon"]
+        "#,
+    );
+
+    assert!(settings
+        .evaluate(&chain_label_data("polygon", "dao", "x", 10))
+        .is_accepted());
+    assert_eq!(
+        settings
+            .evaluate(&chain_label_data("bnb", "dao", "x", 10))
+            .to_string(),
+        "rejected by chains"
+    );
+}
@@ -0,0 +1,103 @@
+use std::{io::Read, path::Path};
+
+use anyhow::{anyhow, bail};
+use reqwest::Client;
+
+use super::LabelData;
+use crate::{
+    backend::ApiBackend,
+    configuration::{BackendKind, CatalogSource, DuneSettings},
+    domain::*,
+};
+
+/// Load every label category of `source`, unfiltered, through the API `dune.backend` selects.
+pub(crate) async fn load(
+    client: &Client,
+    dune: &DuneSettings,
+    source: &CatalogSource,
+) -> Result<Vec<LabelData>, anyhow::Error> {
+    match source {
+        // The API key only opens the REST API, the web GraphQL API is left alone.
+        CatalogSource::ResultId(_) if dune.backend() == BackendKind::Api => {
+            bail!("the api backend cannot load a catalog by `result_id`, use a `query_id` or a `file`")
+        }
+        CatalogSource::QueryId(query_id) if dune.backend() == BackendKind::Api => {
+            ApiBackend::new(dune.api_key()?, dune.api_url())?
+                .latest_results(*query_id)
+                .await
+                .map_err(Into::into)
+        }
+        CatalogSource::ResultId(result_id) => load_result(client, dune, result_id.clone()).await,
+        CatalogSource::QueryId(query_id) => {
+            let result_id = GetResult::new(*query_id)
+                .execute(client, dune.graphql_url(), None)
+                .await?
+                .result_id()
+                .ok_or_else(|| anyhow!("query {} has no result yet", query_id))?;
+
+            load_result(client, dune, result_id).await
+        }
+        CatalogSource::File(path) => load_file(path),
+    }
+}
+
+async fn load_result(
+    client: &Client,
+    dune: &DuneSettings,
+    result_id: String,
+) -> Result<Vec<LabelData>, anyhow::Error> {
+    FindResultDataByResultId::<LabelData>::new(result_id)
+        .execute(client, dune.graphql_url(), None)
+        .await
+        .map(FindResultDataResponse::data)
+        .map_err(Into::into)
+}
+
+fn load_file(path: &Path) -> Result<Vec<LabelData>, anyhow::Error> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| anyhow!("failed to open catalog {}, {}", path.display(), err))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => from_csv(file),
+        Some("json") => serde_json::from_reader(file).map_err(Into::into),
+        _ => bail!(
+            "unsupported catalog file {}, expected a .csv or .json file",
+            path.display()
+        ),
+    }
+}
+
+fn from_csv(reader: impl Read) -> Result<Vec<LabelData>, anyhow::Error> {
+    csv::Reader::from_reader(reader)
+        .into_deserialize()
+        .collect::<Result<_, _>>()
+        .map_err(Into::into)
+}
+
+#[test]
+fn catalog_from_csv_should_ok() {
+    let csv = "label_type,label_name,amount\nsocial,gitcoin grantee,1024\ndao,\"Ol' Bob, Inc\",7\n";
+
+    let data = from_csv(csv.as_bytes()).unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[1].label_type(), "dao");
+    assert_eq!(data[1].label_name(), "Ol' Bob, Inc");
+    assert_eq!(data[1].amount(), 7);
+    assert_eq!(data[1].blockchain(), "ethereum");
+}
+
+#[test]
+fn catalog_from_csv_should_read_the_blockchain() {
+    let csv = "blockchain,label_type,label_name,amount\npolygon,dao,x,3\n";
+
+    assert_eq!(from_csv(csv.as_bytes()).unwrap()[0].blockchain(), "polygon");
+}
+
+#[test]
+fn catalog_from_json_should_ok() {
+    let json = r#"[{"label_type": "social", "label_name": "gitcoin grantee", "amount": 1024}]"#;
+
+    let data = serde_json::from_str::<Vec<LabelData>>(json).unwrap();
+    assert_eq!(data[0].label_name(), "gitcoin grantee");
+    assert_eq!(data[0].amount(), 1024);
+}
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub(crate) struct Cli {
+    /// Base configuration file
+    #[arg(long, global = true, default_value = "config.yaml")]
+    config: PathBuf,
+    /// Environment overlay merged over the base file, e.g. `production` for `production.yaml`
+    #[arg(long, global = true, env = "DUNE_CRAWLER_ENVIRONMENT")]
+    environment: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Run the migrations, fetch the label catalog and crawl every category into the database
+    Crawl {
+        /// Print the queries the crawl would run instead of running them
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, value_enum, default_value_t = PlanFormat::Table, requires = "dry_run")]
+        format: PlanFormat,
+    },
+    /// Print the label categories that would be crawled
+    Catalog {
+        /// Print every category with the rule that accepted or rejected it
+        #[arg(long)]
+        explain: bool,
+    },
+    /// Export the crawled labels
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Show how many labels have been crawled
+    Status,
+    /// Run the pending database migrations
+    Migrate,
+    /// Check that the database, its migrations and the Dune session are usable
+    Check,
+    /// Archive the queries crawls left behind on Dune
+    Cleanup {
+        /// Delete the queries, archived ones included, instead of archiving them
+        #[arg(long)]
+        delete: bool,
+        /// Only clean up queries saved this many hours ago or earlier, newer ones may belong to a
+        /// crawl still running
+        #[arg(long, value_name = "HOURS", default_value_t = 24)]
+        older_than: u32,
+        /// Only list the queries
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum ExportFormat {
+    Csv,
+    #[value(name = "jsonl")]
+    JsonLines,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum PlanFormat {
+    Table,
+    Json,
+}
+
+impl Cli {
+    pub(crate) fn config(&self) -> &Path {
+        &self.config
+    }
+
+    pub(crate) fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    pub(crate) fn command(&self) -> &Command {
+        &self.command
+    }
+}
@@ -0,0 +1,444 @@
+use std::{future::Future, sync::Arc, time::Instant};
+
+use chrono::Utc;
+use futures_util::{stream, Stream, StreamExt};
+use log::{info, warn};
+use secrecy::Secret;
+use serde::de::DeserializeOwned;
+
+use crate::{backend::*, configuration::*, domain::*};
+
+/// Runs SQL on Dune, through the GraphQL API of its web app signed in with a browser session, or
+/// through its public REST API signed in with an API key.
+///
+/// Every request is retried on transient failures, and the session is renewed as it expires.
+/// Saved queries, queued jobs, retries and cleanups are reported through the `log` facade.
+#[derive(Clone)]
+pub struct DuneClient {
+    backend: Arc<Backend>,
+    retry: RetrySettings,
+    jobs: JobSettings,
+}
+
+impl DuneClient {
+    /// A client signed in with the Dune session `cookie` (`csrf=...; auth-refresh=...`) of the user
+    /// `userid`, saving queries for `engine`, whose dialect the SQL it runs must be written in.
+    pub fn new(
+        cookie: impl Into<String>,
+        userid: i32,
+        engine: Engine,
+    ) -> Result<Self, anyhow::Error> {
+        let dune = DuneSettings::default();
+        Self::with_urls(
+            cookie,
+            userid,
+            engine,
+            dune.graphql_url(),
+            dune.session_url(),
+        )
+    }
+
+    /// Like [`DuneClient::new`], talking to `graphql_url` and `session_url` instead of Dune.
+    pub fn with_urls(
+        cookie: impl Into<String>,
+        userid: i32,
+        engine: Engine,
+        graphql_url: impl Into<String>,
+        session_url: impl Into<String>,
+    ) -> Result<Self, anyhow::Error> {
+        let application = ApplicationSettings::with_cookie(Secret::new(cookie.into()), userid);
+        let dune = DuneSettings::new(graphql_url.into(), session_url.into(), engine);
+
+        let web = WebBackend::new(&application, &dune)?;
+
+        Ok(Self::with_backend(Backend::Web(Box::new(web))))
+    }
+
+    /// A client of the public Dune API, signed in with `api_key`, running DuneSQL.
+    pub fn with_api_key(api_key: impl Into<String>) -> Result<Self, anyhow::Error> {
+        Self::with_api_url(api_key, DuneSettings::default().api_url())
+    }
+
+    /// Like [`DuneClient::with_api_key`], talking to `api_url` instead of Dune.
+    pub fn with_api_url(
+        api_key: impl Into<String>,
+        api_url: impl AsRef<str>,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self::with_backend(Backend::Api(ApiBackend::new(
+            Secret::new(api_key.into()),
+            api_url.as_ref(),
+        )?)))
+    }
+
+    pub(crate) fn from_settings(settings: &Settings) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            backend: Arc::new(Backend::from_settings(settings)?),
+            retry: settings.retry().clone(),
+            jobs: settings.jobs().clone(),
+        })
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            retry: RetrySettings::default(),
+            jobs: JobSettings::default(),
+        }
+    }
+
+    /// Run `sql` and stream its rows as `T`s, deserialized from the column names.
+    ///
+    /// The SQL is saved as a query of its own, archived once its result is fetched.
+    pub fn run_sql<T>(&self, sql: &str) -> impl Stream<Item = Result<T, DuneError>> + '_
+    where
+        T: DeserializeOwned + Send,
+    {
+        let sql = sql.to_owned();
+
+        stream::once(async move {
+            let query_id = self.save_query(SQL_QUERY_NAME, sql, Vec::new()).await?;
+            let rows = self.run_query(query_id, Vec::new()).await;
+
+            if let Err(err) = self.discard_query(query_id, QueryCleanup::Archive).await {
+                warn!("failed to archive query {}, {}", query_id, err);
+            }
+
+            rows
+        })
+        .map(|rows| match rows {
+            Ok(rows) => stream::iter(rows.into_iter().map(Ok)).left_stream(),
+            Err(err) => stream::iter(Some(Err(err))).right_stream(),
+        })
+        .flatten()
+    }
+
+    /// Save `sql` as a query named `name`, its `{{key}}` placeholders defaulting to `parameters`.
+    pub(crate) async fn save_query(
+        &self,
+        name: &'static str,
+        sql: String,
+        parameters: Vec<Parameter>,
+    ) -> Result<i32, DuneError> {
+        self.send("save query", || {
+            self.backend.save_query(name, &sql, &parameters)
+        })
+        .await
+    }
+
+    /// Execute the saved query `query_id`, wait for the job and fetch its rows.
+    pub(crate) async fn run_query<T>(
+        &self,
+        query_id: i32,
+        parameters: Vec<Parameter>,
+    ) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let job_id = self.execute(query_id, parameters).await?;
+
+        self.wait(&job_id).await?;
+        self.fetch(job_id).await
+    }
+
+    /// Start a job running the saved query `query_id`.
+    pub(crate) async fn execute(
+        &self,
+        query_id: i32,
+        parameters: Vec<Parameter>,
+    ) -> Result<String, DuneError> {
+        self.send("execute query", || {
+            self.backend.execute(query_id, &parameters)
+        })
+        .await
+    }
+
+    /// Archive or delete the saved query `query_id`, as `cleanup` says.
+    pub(crate) async fn discard_query(
+        &self,
+        query_id: i32,
+        cleanup: QueryCleanup,
+    ) -> Result<(), DuneError> {
+        let action = match cleanup {
+            QueryCleanup::Archive => "archived",
+            QueryCleanup::Delete => "deleted",
+            QueryCleanup::Keep => return Ok(()),
+        };
+
+        match self
+            .send("discard query", || {
+                self.backend.discard_query(query_id, cleanup)
+            })
+            .await?
+        {
+            true => info!("{} query {}", action, query_id),
+            false => info!("query {} is already gone", query_id),
+        }
+
+        Ok(())
+    }
+
+    /// The queries crawls saved, archived ones too when `include_archived`.
+    pub(crate) async fn crawler_queries(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<CrawlerQuery>, DuneError> {
+        self.send("find crawler queries", || {
+            self.backend.crawler_queries(include_archived)
+        })
+        .await
+    }
+
+    /// Poll the job until it is done, cancelling it once it has waited too long.
+    pub(crate) async fn wait(&self, job_id: &str) -> Result<(), DuneError> {
+        let started = Instant::now();
+        let mut polls = 0;
+
+        loop {
+            let (position, locked_until) = match self
+                .send("poll job", || self.backend.status(job_id))
+                .await?
+            {
+                JobStatus::Running {
+                    position,
+                    locked_until,
+                } => (position, locked_until),
+                JobStatus::Finished => return Ok(()),
+            };
+
+            let waited = started.elapsed();
+            let unlocked = locked_until.is_some_and(|locked_until| locked_until < Utc::now());
+            if waited > self.jobs.max_wait() || unlocked {
+                warn!(
+                    "job {} still running after {:?}, cancelling it",
+                    job_id, waited
+                );
+
+                // Nothing was cancelled when the job finished in the meantime.
+                return match self
+                    .send("cancel job", || self.backend.cancel(job_id))
+                    .await?
+                {
+                    true => Err(DuneError::JobTimedOut {
+                        job_id: job_id.to_owned(),
+                        waited,
+                    }),
+                    false => Ok(()),
+                };
+            }
+
+            if let Some(position) = position {
+                info!("job {} is queued at {}", job_id, position);
+            }
+
+            polls += 1;
+            tokio::time::sleep(self.jobs.poll_interval(polls)).await;
+        }
+    }
+
+    /// The rows of the finished job, or the SQL error it failed with.
+    pub(crate) async fn fetch<T>(&self, job_id: String) -> Result<Vec<T>, DuneError>
+    where
+        T: DeserializeOwned + Send,
+    {
+        self.send("fetch results", || self.backend.fetch(&job_id))
+            .await
+    }
+
+    /// Run `request` against the backend, retrying transient failures with backoff.
+    async fn send<R, F, Fut>(&self, action: &str, request: F) -> Result<R, DuneError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, DuneError>>,
+    {
+        let mut attempts = 0;
+
+        loop {
+            match request().await {
+                Err(err) if err.is_transient() && attempts < self.retry.max_retries() => {
+                    attempts += 1;
+                    let delay = err
+                        .retry_after()
+                        .unwrap_or_else(|| self.retry.delay(attempts));
+
+                    warn!(
+                        "{} failed, {}, retry {}/{} in {:?}",
+                        action,
+                        err,
+                        attempts,
+                        self.retry.max_retries(),
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn stub_client(url: &str, retry: serde_json::Value, jobs: serde_json::Value) -> DuneClient {
+    DuneClient {
+        retry: serde_json::from_value(retry).unwrap(),
+        jobs: serde_json::from_value(jobs).unwrap(),
+        ..DuneClient::with_api_url("key", url).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn send_should_retry_transient_failures() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::stub::{response, serve, serve_in_turn};
+
+    let executed = response("200 OK", &[], r#"{"execution_id":"exec-1"}"#);
+
+    let (url, requests) = serve_in_turn(vec![
+        response("502 Bad Gateway", &[], "<html>"),
+        executed.clone(),
+    ])
+    .await;
+    let client = stub_client(
+        &url,
+        serde_json::json!({"initial_backoff_ms": 1}),
+        serde_json::json!({}),
+    );
+    assert_eq!(client.execute(1, Vec::new()).await.unwrap(), "exec-1");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Backing off would take a minute, so the retry must wait for `Retry-After` only.
+    let (url, requests) = serve_in_turn(vec![
+        response("429 Too Many Requests", &["retry-after: 0"], ""),
+        executed,
+    ])
+    .await;
+    let client = stub_client(
+        &url,
+        serde_json::json!({"initial_backoff_ms": 60000}),
+        serde_json::json!({}),
+    );
+    let started = Instant::now();
+    assert_eq!(client.execute(1, Vec::new()).await.unwrap(), "exec-1");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    let (url, requests) = serve_in_turn(vec![response("502 Bad Gateway", &[], "<html>")]).await;
+    let client = stub_client(
+        &url,
+        serde_json::json!({"max_retries": 2, "initial_backoff_ms": 1}),
+        serde_json::json!({}),
+    );
+    assert!(matches!(
+        client.execute(1, Vec::new()).await,
+        Err(DuneError::Status { status, .. }) if status.as_u16() == 502
+    ));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // A failed poll is retried on its own, the job is not started again.
+    let executes = Arc::new(AtomicUsize::new(0));
+    let polls = Arc::new(AtomicUsize::new(0));
+    let url = {
+        let (executes, polls) = (executes.clone(), polls.clone());
+        serve(
+            move |request| match request.lines().next().unwrap_or_default() {
+                line if line.contains("/execute") => {
+                    executes.fetch_add(1, Ordering::SeqCst);
+                    response("200 OK", &[], r#"{"execution_id":"exec-1"}"#)
+                }
+                line if line.contains("/status") && polls.fetch_add(1, Ordering::SeqCst) == 0 => {
+                    response("503 Service Unavailable", &[], "")
+                }
+                line if line.contains("/status") => {
+                    response("200 OK", &[], r#"{"state":"QUERY_STATE_COMPLETED"}"#)
+                }
+                _ => response(
+                    "200 OK",
+                    &[],
+                    r#"{"state":"QUERY_STATE_COMPLETED","result":{"rows":[1,2]}}"#,
+                ),
+            },
+        )
+        .await
+    };
+    let client = stub_client(
+        &url,
+        serde_json::json!({"initial_backoff_ms": 1}),
+        serde_json::json!({}),
+    );
+    assert_eq!(
+        client.run_query::<i32>(1, Vec::new()).await.unwrap(),
+        vec![1, 2]
+    );
+    assert_eq!(executes.load(Ordering::SeqCst), 1);
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    // Permanent failures are not sent again.
+    let (url, requests) = serve_in_turn(vec![response("400 Bad Request", &[], "{}")]).await;
+    let client = stub_client(
+        &url,
+        serde_json::json!({"initial_backoff_ms": 1}),
+        serde_json::json!({}),
+    );
+    assert!(client.execute(1, Vec::new()).await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn wait_should_cancel_jobs_running_too_long() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::stub::{response, serve};
+
+    // A web session whose job keeps running, `locked_until` as given.
+    let stub = |locked_until: &'static str, cancelled: &'static str| async move {
+        let cancels = Arc::new(AtomicUsize::new(0));
+        let count = cancels.clone();
+        let url = serve(move |request| {
+            let body = if request.starts_with("POST /session") {
+                String::from(r#"{"token":"token"}"#)
+            } else if request.contains(r#""operationName":"CancelQuery""#) {
+                count.fetch_add(1, Ordering::SeqCst);
+                format!(r#"{{"data":{{"cancel_query":{}}}}}"#, cancelled)
+            } else {
+                format!(
+                    r#"{{"data":{{"view_queue_positions":[],"jobs_by_pk":{{"category":"execute","locked_until":{}}}}}}}"#,
+                    locked_until
+                )
+            };
+            response("200 OK", &[], &body)
+        })
+        .await;
+        (url, cancels)
+    };
+    let web_client = |url: &str, jobs| DuneClient {
+        jobs: serde_json::from_value(jobs).unwrap(),
+        ..DuneClient::with_urls(
+            "auth-refresh=x",
+            1,
+            Engine::Postgres,
+            format!("{}/graphql", url),
+            format!("{}/session", url),
+        )
+        .unwrap()
+    };
+
+    let (url, cancels) = stub("null", r#"{"job_id":"job-1"}"#).await;
+    let client = web_client(&url, serde_json::json!({"max_wait_secs": 0}));
+    assert!(matches!(
+        client.wait("job-1").await,
+        Err(DuneError::JobTimedOut { job_id, .. }) if job_id == "job-1"
+    ));
+    assert_eq!(cancels.load(Ordering::SeqCst), 1);
+
+    // Dune stopped waiting for the job, which finished before it could be cancelled.
+    let (url, cancels) = stub(r#""2022-06-16T13:31:04.594428+00:00""#, "null").await;
+    let client = web_client(&url, serde_json::json!({}));
+    assert!(client.wait("job-1").await.is_ok());
+    assert_eq!(cancels.load(Ordering::SeqCst), 1);
+}
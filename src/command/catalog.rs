@@ -0,0 +1,46 @@
+use reqwest::Client;
+
+use crate::{catalog, configuration::*};
+
+pub(crate) async fn catalog(settings: &Settings, explain: bool) -> anyhow::Result<()> {
+    if explain {
+        let categories =
+            catalog::load(&Client::new(), settings.dune(), settings.catalog().source()).await?;
+
+        println!(
+            "{:<12} {:<32} {:<48} {:>10}  VERDICT",
+            "CHAIN", "TYPE", "NAME", "AMOUNT"
+        );
+        for data in &categories {
+            println!(
+                "{:<12} {:<32} {:<48} {:>10}  {}",
+                data.blockchain(),
+                data.label_type(),
+                data.label_name(),
+                data.amount(),
+                settings.catalog().evaluate(data)
+            );
+        }
+
+        return Ok(());
+    }
+
+    let categories = catalog::fetch(&Client::new(), settings.dune(), settings.catalog()).await?;
+
+    println!(
+        "{:<12} {:<32} {:<48} {:>10}",
+        "CHAIN", "TYPE", "NAME", "AMOUNT"
+    );
+    for data in &categories {
+        println!(
+            "{:<12} {:<32} {:<48} {:>10}",
+            data.blockchain(),
+            data.label_type(),
+            data.label_name(),
+            data.amount()
+        );
+    }
+    println!("categories: {}", categories.len());
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+use sqlx::{migrate::Migrate, PgPool};
+
+use crate::{backend::ApiBackend, configuration::*, get_connection_pool, session::*};
+
+/// Check everything a crawl depends on and fail unless all of it is usable.
+pub(crate) async fn check(settings: &Settings) -> anyhow::Result<()> {
+    let db_pool = get_connection_pool(settings.database());
+
+    let results = [
+        ("database", check_database(&db_pool).await),
+        ("migrations", check_migrations(&db_pool).await),
+        ("dune", check_dune(settings).await),
+    ];
+
+    for (name, result) in &results {
+        match result {
+            Ok(detail) => println!("[ ok ] {}: {}", name, detail),
+            Err(err) => println!("[FAIL] {}: {}", name, err),
+        }
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        bail!("{} of {} checks failed", failed, results.len());
+    }
+
+    Ok(())
+}
+
+async fn check_database(db_pool: &PgPool) -> anyhow::Result<String> {
+    let version = sqlx::query_scalar!(r#"SELECT version() AS "version!""#)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(version)
+}
+
+async fn check_migrations(db_pool: &PgPool) -> anyhow::Result<String> {
+    let migrator = sqlx::migrate!();
+
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = '_sqlx_migrations') AS "exists!""#
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    let applied = if exists {
+        db_pool
+            .acquire()
+            .await?
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let pending = migrator
+        .iter()
+        .filter(|migration| {
+            !migration.migration_type.is_down_migration() && !applied.contains(&migration.version)
+        })
+        .map(|migration| format!("{} {}", migration.version, migration.description))
+        .collect::<Vec<_>>();
+
+    if !pending.is_empty() {
+        bail!("{} pending, {}", pending.len(), pending.join(", "));
+    }
+
+    Ok(format!("{} applied", applied.len()))
+}
+
+async fn check_dune(settings: &Settings) -> anyhow::Result<String> {
+    let dune = settings.dune();
+
+    match dune.backend() {
+        BackendKind::Web => {
+            SessionManager::new(settings.application(), dune)?
+                .refresh()
+                .await?;
+
+            Ok(String::from("got a bearer token"))
+        }
+        BackendKind::Api => {
+            ApiBackend::new(dune.api_key()?, dune.api_url())?
+                .check_key()
+                .await?;
+
+            Ok(format!("api key accepted by {}", dune.api_url()))
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::bail;
+use chrono::{Duration, Utc};
+
+use crate::{client::DuneClient, configuration::*};
+
+/// Find the queries crawls saved on Dune at least `older_than` hours ago and archive or delete them.
+///
+/// Newer queries are left alone, a crawl still running would fail once its query is gone.
+pub(crate) async fn cleanup(
+    settings: &Settings,
+    delete: bool,
+    older_than: u32,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let client = DuneClient::from_settings(settings)?;
+
+    let cutoff = Utc::now() - Duration::hours(older_than.into());
+    let (queries, recent): (Vec<_>, Vec<_>) = client
+        .crawler_queries(delete)
+        .await?
+        .into_iter()
+        .partition(|query| query.created_at() <= cutoff);
+
+    for query in &queries {
+        println!(
+            "{}  {}  {}{}",
+            query.id(),
+            query.created_at(),
+            query.name(),
+            if query.is_archived() {
+                " (archived)"
+            } else {
+                ""
+            }
+        );
+    }
+    println!("queries: {}", queries.len());
+    if !recent.is_empty() {
+        println!(
+            "skipped {} queries saved in the last {} hours",
+            recent.len(),
+            older_than
+        );
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let cleanup = if delete {
+        QueryCleanup::Delete
+    } else {
+        QueryCleanup::Archive
+    };
+
+    let mut failed = 0;
+    for query in &queries {
+        if let Err(err) = client.discard_query(query.id(), cleanup).await {
+            println!("failed to clean up query {}, {}", query.id(), err);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "{} of {} queries were not cleaned up",
+            failed,
+            queries.len()
+        );
+    }
+
+    Ok(())
+}
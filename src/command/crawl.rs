@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::sync::watch;
+
+use crate::{
+    catalog,
+    client::DuneClient,
+    configuration::*,
+    domain::{AddressLabel, CRAWLER_QUERY_NAME},
+    get_connection_pool,
+    query_task::*,
+    scheduler::schedule,
+};
+
+/// How often the progress of the running tasks is printed.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+pub(crate) async fn crawl(settings: Settings) -> anyhow::Result<()> {
+    let db_pool = get_connection_pool(settings.database());
+
+    sqlx::migrate!().run(&db_pool).await?;
+
+    let categories = catalog::fetch(&Client::new(), settings.dune(), settings.catalog()).await?;
+    let client = DuneClient::from_settings(&settings)?;
+    let engine = settings.dune().engine();
+    let query_id = client
+        .save_query(
+            CRAWLER_QUERY_NAME,
+            label_query(engine),
+            label_parameters(engine, "", "", "", None, 0),
+        )
+        .await?;
+
+    let tasks = categories
+        .into_iter()
+        .map(|data| QueryTask::new(client.clone(), engine, query_id, data))
+        .collect::<Vec<_>>();
+    let progress = tokio::spawn(report_progress(
+        tasks
+            .iter()
+            .map(|task| (task.category(), task.phase()))
+            .collect(),
+    ));
+
+    tokio::select! {
+        _ = run(&db_pool, tasks, settings.jobs().concurrency()) => {}
+        _ = shutdown() => println!("interrupted"),
+    }
+    progress.abort();
+
+    if let Err(err) = client
+        .discard_query(query_id, settings.application().query_cleanup())
+        .await
+    {
+        println!("failed to clean up query {}, {}", query_id, err);
+    }
+
+    Ok(())
+}
+
+/// Resolves once the process is asked to stop, with Ctrl-C or, as service managers do, SIGTERM.
+async fn shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(err) => println!("failed to listen for SIGTERM, {}", err),
+        }
+    }
+
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        println!("failed to listen for Ctrl-C, {}", err);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Save every page the tasks yield, at most `concurrency` tasks running at once.
+async fn run(db_pool: &PgPool, tasks: Vec<QueryTask>, concurrency: usize) {
+    // Pages of several categories come interleaved, each is told apart by its category.
+    let tasks = tasks.into_iter().map(|task| {
+        let category = task.category();
+        task.into_stream().map(move |page| (category.clone(), page))
+    });
+    let pages = schedule(tasks, concurrency);
+    let mut pages = std::pin::pin!(pages);
+
+    while let Some((category, page)) = pages.next().await {
+        match page {
+            Ok(labels) => match save_labels(db_pool, &labels).await {
+                Ok(()) => println!("{}: new labels, count: {}", category, labels.len()),
+                Err(err) => println!(
+                    "{}: failed to save {} labels, {}",
+                    category,
+                    labels.len(),
+                    err
+                ),
+            },
+            Err(err) => println!("{}: failed, {}", category, err),
+        }
+    }
+}
+
+async fn save_labels(db_pool: &PgPool, labels: &[AddressLabel]) -> Result<(), sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+
+    for record in labels {
+        let details = record.details();
+        if let Err(err) = sqlx::query!(
+            "INSERT INTO dune_labels(blockchain, address, label_type, label_name, category, contributor, source, created_at, updated_at, model_name) VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            record.blockchain(),
+            record.address(),
+            record.label_type(),
+            record.label_name(),
+            details.category(),
+            details.contributor(),
+            details.source(),
+            details.created_at(),
+            details.updated_at(),
+            details.model_name()
+        )
+        .execute(&mut transaction)
+        .await
+        {
+            println!("failed to insert new record, {}", err);
+        }
+    }
+
+    transaction.commit().await
+}
+
+async fn report_progress(tasks: Vec<(String, watch::Receiver<TaskPhase>)>) {
+    let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let phases = tasks
+            .iter()
+            .map(|(category, phase)| (category, phase.borrow().clone()))
+            .collect::<Vec<_>>();
+        let count = |step| {
+            phases
+                .iter()
+                .filter(|(_, phase)| phase.step() == step)
+                .count()
+        };
+
+        println!(
+            "progress: {} done, {} failed, {} pending of {} categories, {} rows remaining",
+            count(TaskStep::Done),
+            count(TaskStep::Failed),
+            count(TaskStep::Pending),
+            phases.len(),
+            phases
+                .iter()
+                .map(|(_, phase)| phase.rows_remaining())
+                .sum::<usize>()
+        );
+        for (category, phase) in &phases {
+            if matches!(
+                phase.step(),
+                TaskStep::Executing | TaskStep::Waiting | TaskStep::Fetching
+            ) {
+                println!("  {}: {}", category, phase);
+            }
+        }
+    }
+}
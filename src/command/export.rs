@@ -0,0 +1,91 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+
+use crate::{cli::ExportFormat, configuration::*, get_connection_pool};
+
+pub(crate) async fn export(
+    settings: &Settings,
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let db_pool = get_connection_pool(settings.database());
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut rows = sqlx::query!(
+        "SELECT blockchain, address, label_type, label_name, category, contributor, source, created_at, updated_at, model_name FROM dune_labels ORDER BY blockchain, address, id"
+    )
+    .fetch(&db_pool);
+
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record([
+                "blockchain",
+                "address",
+                "label_type",
+                "label_name",
+                "category",
+                "contributor",
+                "source",
+                "created_at",
+                "updated_at",
+                "model_name",
+            ])?;
+            while let Some(row) = rows.try_next().await? {
+                let timestamp = |timestamp: Option<DateTime<Utc>>| {
+                    timestamp
+                        .map(|timestamp| timestamp.to_rfc3339())
+                        .unwrap_or_default()
+                };
+
+                writer.write_record([
+                    row.blockchain.as_str(),
+                    &row.address,
+                    &row.label_type,
+                    &row.label_name,
+                    row.category.as_deref().unwrap_or_default(),
+                    row.contributor.as_deref().unwrap_or_default(),
+                    row.source.as_deref().unwrap_or_default(),
+                    &timestamp(row.created_at),
+                    &timestamp(row.updated_at),
+                    row.model_name.as_deref().unwrap_or_default(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::JsonLines => {
+            let mut writer = writer;
+            while let Some(row) = rows.try_next().await? {
+                serde_json::to_writer(
+                    &mut writer,
+                    &serde_json::json!({
+                        "blockchain": row.blockchain,
+                        "address": row.address,
+                        "label_type": row.label_type,
+                        "label_name": row.label_name,
+                        "category": row.category,
+                        "contributor": row.contributor,
+                        "source": row.source,
+                        "created_at": row.created_at,
+                        "updated_at": row.updated_at,
+                        "model_name": row.model_name,
+                    }),
+                )?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Prints what the client reports to stdout, next to the output of the commands.
+struct StdoutLogger;
+
+impl Log for StdoutLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= LevelFilter::Info && metadata.target().starts_with("dune_crawler")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            println!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Print the events [`DuneClient`](crate::DuneClient) logs, the library itself never prints them.
+pub(crate) fn init_logger() {
+    if log::set_logger(&StdoutLogger).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
@@ -0,0 +1,10 @@
+use crate::{configuration::*, get_connection_pool};
+
+pub(crate) async fn migrate(settings: &Settings) -> anyhow::Result<()> {
+    let db_pool = get_connection_pool(settings.database());
+
+    sqlx::migrate!().run(&db_pool).await?;
+    println!("migrations are up to date");
+
+    Ok(())
+}
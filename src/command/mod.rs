@@ -0,0 +1,19 @@
+mod catalog;
+mod check;
+mod cleanup;
+mod crawl;
+mod export;
+mod logger;
+mod migrate;
+mod plan;
+mod status;
+
+pub(crate) use catalog::*;
+pub(crate) use check::*;
+pub(crate) use cleanup::*;
+pub(crate) use crawl::*;
+pub(crate) use export::*;
+pub(crate) use logger::*;
+pub(crate) use migrate::*;
+pub(crate) use plan::*;
+pub(crate) use status::*;
@@ -0,0 +1,139 @@
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::{
+    catalog::{self, LabelData},
+    cli::PlanFormat,
+    configuration::*,
+    domain::Parameter,
+    query_task::*,
+};
+
+/// Stands in for the last address of the previous page, only known once that page is fetched.
+const CURSOR_PLACEHOLDER: &str = "<cursor>";
+
+#[derive(Serialize)]
+struct PagePlan {
+    page: usize,
+    limit: usize,
+    parameters: Vec<Parameter>,
+}
+
+#[derive(Serialize)]
+struct CategoryPlan {
+    blockchain: String,
+    label_type: String,
+    label_name: String,
+    amount: usize,
+    pages: Vec<PagePlan>,
+}
+
+impl CategoryPlan {
+    fn new(engine: Engine, data: LabelData) -> Self {
+        let (blockchain, label_type, label_name, amount) = data.into_parts();
+        let mut pages = Vec::new();
+        let mut remaining = amount;
+
+        while remaining > 0 {
+            let limit = page_limit(remaining);
+            let base_address = (!pages.is_empty()).then_some(CURSOR_PLACEHOLDER);
+
+            pages.push(PagePlan {
+                page: pages.len() + 1,
+                limit,
+                parameters: label_parameters(
+                    engine,
+                    &blockchain,
+                    &label_type,
+                    &label_name,
+                    base_address,
+                    limit,
+                ),
+            });
+            remaining -= limit;
+        }
+
+        Self {
+            blockchain,
+            label_type,
+            label_name,
+            amount,
+            pages,
+        }
+    }
+}
+
+/// Print what `crawl` would run, without executing anything on Dune or touching the database.
+pub(crate) async fn plan(settings: &Settings, format: PlanFormat) -> anyhow::Result<()> {
+    let engine = settings.dune().engine();
+    let plans = catalog::fetch(&Client::new(), settings.dune(), settings.catalog())
+        .await?
+        .into_iter()
+        .map(|data| CategoryPlan::new(engine, data))
+        .collect::<Vec<_>>();
+
+    match format {
+        PlanFormat::Table => {
+            println!("engine: {:?}", engine);
+            println!("query: {}", label_query(engine));
+            for plan in &plans {
+                println!(
+                    "{} / {} / {}: {} labels, {} pages",
+                    plan.blockchain,
+                    plan.label_type,
+                    plan.label_name,
+                    plan.amount,
+                    plan.pages.len()
+                );
+                for page in &plan.pages {
+                    println!(
+                        "  {:>4}  {}",
+                        page.page,
+                        page.parameters
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    );
+                }
+            }
+            println!(
+                "categories: {}, executions: {}",
+                plans.len(),
+                plans.iter().map(|plan| plan.pages.len()).sum::<usize>()
+            );
+        }
+        PlanFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "engine": engine,
+                "query": label_query(engine),
+                "categories": plans,
+            }))?
+        ),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn category_plan_should_page_by_page_size() {
+    let data = serde_json::from_value(serde_json::json!({
+        "label_type": "dao",
+        "label_name": "x",
+        "amount": PAGE_SIZE * 2 + 5,
+    }))
+    .unwrap();
+    let plan = CategoryPlan::new(Engine::Postgres, data);
+
+    assert_eq!(
+        plan.pages.iter().map(|page| page.limit).collect::<Vec<_>>(),
+        vec![PAGE_SIZE, PAGE_SIZE, 5]
+    );
+    assert_eq!(plan.blockchain, "ethereum");
+    assert_eq!(plan.pages[0].parameters[3].to_string(), "cursor=''");
+    assert_eq!(
+        plan.pages[2].parameters[3].to_string(),
+        r"cursor=E'\\x<cursor>'"
+    );
+}
@@ -0,0 +1,22 @@
+use crate::{configuration::*, get_connection_pool};
+
+pub(crate) async fn status(settings: &Settings) -> anyhow::Result<()> {
+    let db_pool = get_connection_pool(settings.database());
+
+    let rows = sqlx::query!(
+        r#"SELECT blockchain, label_type, COUNT(*) AS "count!" FROM dune_labels GROUP BY blockchain, label_type ORDER BY blockchain, label_type"#
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    println!("{:<12} {:<32} {:>10}", "CHAIN", "TYPE", "LABELS");
+    for row in &rows {
+        println!(
+            "{:<12} {:<32} {:>10}",
+            row.blockchain, row.label_type, row.count
+        );
+    }
+    println!("total: {}", rows.iter().map(|row| row.count).sum::<i64>());
+
+    Ok(())
+}
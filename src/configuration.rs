@@ -1,71 +1,639 @@
-use config::{Config, File};
-use secrecy::{ExposeSecret, Secret};
-use serde::Deserialize;
-use serde_aux::field_attributes::deserialize_number_from_string;
-use sqlx::postgres::{PgConnectOptions, PgSslMode};
-
-#[derive(Debug, Deserialize)]
-pub(crate) struct Settings {
-    application: ApplicationSettings,
-    database: DatabaseSettings,
-}
-
-#[derive(Debug, Deserialize)]
-pub(crate) struct ApplicationSettings {
-    cookie: String,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
-    userid: i32,
-}
-
-#[derive(Debug, Deserialize)]
-pub(crate) struct DatabaseSettings {
-    username: String,
-    password: Secret<String>,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
-    port: u16,
-    host: String,
-    name: String,
-}
-
-impl ApplicationSettings {
-    pub(crate) fn cookie(&self) -> &str {
-        &self.cookie
-    }
-
-    pub(crate) fn userid(&self) -> i32 {
-        self.userid
-    }
-}
-
-impl DatabaseSettings {
-    pub(crate) fn without_db(&self) -> PgConnectOptions {
-        PgConnectOptions::new()
-            .host(&self.host)
-            .username(&self.username)
-            .password(self.password.expose_secret())
-            .port(self.port)
-            .ssl_mode(PgSslMode::Prefer)
-    }
-
-    pub(crate) fn with_db(&self) -> PgConnectOptions {
-        self.without_db().database(&self.name)
-    }
-}
-
-impl Settings {
-    pub(crate) fn new() -> Result<Self, anyhow::Error> {
-        Config::builder()
-            .add_source(File::with_name("./config.yaml").required(true))
-            .build()
-            .and_then(|config| config.try_deserialize())
-            .map_err(Into::into)
-    }
-
-    pub(crate) fn application(&self) -> &ApplicationSettings {
-        &self.application
-    }
-
-    pub(crate) fn database(&self) -> &DatabaseSettings {
-        &self.database
-    }
-}
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use config::{Config, Environment, File};
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_number_from_string;
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+
+use crate::catalog::{CatalogRules, LabelData, Reason, Verdict, ETHEREUM};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Settings {
+    application: ApplicationSettings,
+    database: DatabaseSettings,
+    catalog: CatalogSettings,
+    #[serde(default)]
+    dune: DuneSettings,
+    #[serde(default)]
+    retry: RetrySettings,
+    #[serde(default)]
+    jobs: JobSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApplicationSettings {
+    /// The Dune session cookie, usually set through `DUNE_CRAWLER__APPLICATION__COOKIE`
+    cookie: Option<Secret<String>>,
+    /// A file holding the Dune session cookie, used when `cookie` is not set
+    cookie_file: Option<PathBuf>,
+    /// Where the rotated session cookies are kept, used instead of the cookie once it exists and
+    /// until Dune rejects them
+    cookie_jar: Option<PathBuf>,
+    /// Where the bearer token is kept between runs
+    token_cache: Option<PathBuf>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    userid: i32,
+    /// What happens to the query a crawl saved once the crawl ends or is interrupted
+    #[serde(default)]
+    query_cleanup: QueryCleanup,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum QueryCleanup {
+    #[default]
+    Archive,
+    Delete,
+    Keep,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DatabaseSettings {
+    username: String,
+    password: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    port: u16,
+    host: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct DuneSettings {
+    /// Which Dune API saves and runs the label query
+    backend: BackendKind,
+    /// Which engine runs the label query
+    engine: Engine,
+    graphql_url: String,
+    session_url: String,
+    api_url: String,
+    /// The Dune API key, usually set through `DUNE_CRAWLER__DUNE__API_KEY`
+    api_key: Option<Secret<String>>,
+}
+
+impl Default for DuneSettings {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            engine: Engine::default(),
+            graphql_url: String::from("https://core-hsr.duneanalytics.com/v1/graphql"),
+            session_url: String::from("https://dune.com/api/auth/session"),
+            api_url: String::from("https://api.dune.com/api/v1"),
+            api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BackendKind {
+    /// The GraphQL API of the web app, signed in with the session cookie
+    #[default]
+    Web,
+    /// The public REST API, signed in with an API key
+    Api,
+}
+
+/// The engine Dune runs a query on, each with its own SQL dialect and labels table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Engine {
+    /// The legacy PostgreSQL engine, addresses are `bytea`
+    #[default]
+    Postgres,
+    /// Dune engine v2 on Spark SQL, addresses are `0x` strings
+    Spark,
+    /// DuneSQL, addresses are `varbinary`
+    DuneSql,
+}
+
+impl Engine {
+    /// The dataset the web app saves a query for this engine with.
+    pub(crate) fn dataset_id(self) -> i32 {
+        match self {
+            Engine::Postgres => 4,
+            Engine::Spark => 38,
+            Engine::DuneSql => 100,
+        }
+    }
+}
+
+/// How often and how patiently a failed Dune request is sent again.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct RetrySettings {
+    max_retries: u32,
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60000,
+        }
+    }
+}
+
+/// How a queued Dune job is waited for.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct JobSettings {
+    poll_interval_ms: u64,
+    max_poll_interval_ms: u64,
+    max_wait_secs: u64,
+    /// How many label categories are crawled at once, each running one job at a time
+    concurrency: usize,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 1000,
+            max_poll_interval_ms: 10000,
+            max_wait_secs: 1800,
+            concurrency: 2,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CatalogSettings {
+    source: CatalogSource,
+    /// The chains whose categories are crawled
+    #[serde(default = "default_chains")]
+    chains: Vec<String>,
+    #[serde(default)]
+    rules: CatalogRules,
+}
+
+/// Where the list of label categories (`label_type`, `label_name`, `amount`) comes from.
+///
+/// Written as `{kind, value}` rather than a single-key map, so an overlay or environment variable
+/// setting both switches the source instead of being merged into a map of two sources.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "TaggedCatalogSource")]
+pub(crate) enum CatalogSource {
+    /// A Dune query result
+    ResultId(String),
+    /// The latest result of a Dune query
+    QueryId(i32),
+    /// A local `.csv` or `.json` file
+    File(PathBuf),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CatalogSourceKind {
+    ResultId,
+    QueryId,
+    File,
+}
+
+#[derive(Deserialize)]
+struct TaggedCatalogSource {
+    kind: CatalogSourceKind,
+    value: String,
+}
+
+impl TryFrom<TaggedCatalogSource> for CatalogSource {
+    type Error = String;
+
+    fn try_from(source: TaggedCatalogSource) -> Result<Self, Self::Error> {
+        Ok(match source.kind {
+            CatalogSourceKind::ResultId => Self::ResultId(source.value),
+            CatalogSourceKind::QueryId => Self::QueryId(source.value.parse().map_err(|err| {
+                format!(
+                    "invalid query id {:?} for a `query_id` source, {}",
+                    source.value, err
+                )
+            })?),
+            CatalogSourceKind::File => Self::File(PathBuf::from(source.value)),
+        })
+    }
+}
+
+impl ApplicationSettings {
+    /// Settings signing in with `cookie` alone, nothing kept on disk between runs.
+    pub(crate) fn with_cookie(cookie: Secret<String>, userid: i32) -> Self {
+        Self {
+            cookie: Some(cookie),
+            cookie_file: None,
+            cookie_jar: None,
+            token_cache: None,
+            userid,
+            query_cleanup: QueryCleanup::default(),
+        }
+    }
+
+    pub(crate) fn cookie(&self) -> Result<Secret<String>, anyhow::Error> {
+        match (&self.cookie, &self.cookie_file) {
+            (Some(cookie), _) => Ok(cookie.clone()),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map(|cookie| Secret::new(cookie.trim().to_owned()))
+                .map_err(|err| anyhow!("failed to read cookie file {}, {}", path.display(), err)),
+            (None, None) => Err(anyhow!(
+                "neither `application.cookie` nor `application.cookie_file` is set"
+            )),
+        }
+    }
+
+    pub(crate) fn cookie_jar(&self) -> Option<&Path> {
+        self.cookie_jar.as_deref()
+    }
+
+    pub(crate) fn token_cache(&self) -> Option<&Path> {
+        self.token_cache.as_deref()
+    }
+
+    pub(crate) fn userid(&self) -> i32 {
+        self.userid
+    }
+
+    pub(crate) fn query_cleanup(&self) -> QueryCleanup {
+        self.query_cleanup
+    }
+}
+
+impl DatabaseSettings {
+    pub(crate) fn without_db(&self) -> PgConnectOptions {
+        PgConnectOptions::new()
+            .host(&self.host)
+            .username(&self.username)
+            .password(self.password.expose_secret())
+            .port(self.port)
+            .ssl_mode(PgSslMode::Prefer)
+    }
+
+    pub(crate) fn with_db(&self) -> PgConnectOptions {
+        self.without_db().database(&self.name)
+    }
+}
+
+impl DuneSettings {
+    pub(crate) fn new(graphql_url: String, session_url: String, engine: Engine) -> Self {
+        Self {
+            graphql_url,
+            session_url,
+            engine,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn backend(&self) -> BackendKind {
+        self.backend
+    }
+
+    pub(crate) fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    pub(crate) fn graphql_url(&self) -> &str {
+        &self.graphql_url
+    }
+
+    pub(crate) fn session_url(&self) -> &str {
+        &self.session_url
+    }
+
+    pub(crate) fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    pub(crate) fn api_key(&self) -> Result<Secret<String>, anyhow::Error> {
+        self.api_key
+            .clone()
+            .ok_or_else(|| anyhow!("`dune.api_key` is not set"))
+    }
+}
+
+impl RetrySettings {
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The wait before retry number `attempt` (starting at 1): exponential backoff capped at
+    /// `max_backoff_ms`, with jitter in the upper half so tasks failing together spread out.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(32);
+        let ceiling = self
+            .initial_backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms);
+
+        Duration::from_millis(rand::thread_rng().gen_range(ceiling / 2..=ceiling))
+    }
+}
+
+impl JobSettings {
+    /// The wait before poll number `poll` (starting at 1), doubling up to `max_poll_interval_ms`.
+    pub(crate) fn poll_interval(&self, poll: u32) -> Duration {
+        let factor = 1u64 << poll.saturating_sub(1).min(32);
+
+        Duration::from_millis(
+            self.poll_interval_ms
+                .saturating_mul(factor)
+                .min(self.max_poll_interval_ms),
+        )
+    }
+
+    /// How long a job may be queued and running before it is cancelled.
+    pub(crate) fn max_wait(&self) -> Duration {
+        Duration::from_secs(self.max_wait_secs)
+    }
+
+    pub(crate) fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+}
+
+fn default_chains() -> Vec<String> {
+    vec![String::from(ETHEREUM)]
+}
+
+impl CatalogSettings {
+    pub(crate) fn source(&self) -> &CatalogSource {
+        &self.source
+    }
+
+    /// Whether a category gets crawled: its chain must be one of `chains`, then the rules decide.
+    pub(crate) fn evaluate(&self, data: &LabelData) -> Verdict<'_> {
+        match self.chains.iter().any(|chain| chain == data.blockchain()) {
+            true => self.rules.evaluate(data),
+            false => Verdict::Rejected(Reason::ChainNotConfigured),
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings from, in increasing order of precedence:
+    ///
+    /// 1. the base file `path`
+    /// 2. the `<environment>.yaml` overlay next to it, when `environment` is set
+    /// 3. `DUNE_CRAWLER__<SECTION>__<KEY>` environment variables, e.g. `DUNE_CRAWLER__DATABASE__HOST`
+    pub(crate) fn new(path: &Path, environment: Option<&str>) -> Result<Self, anyhow::Error> {
+        Self::with_environment(
+            path,
+            environment,
+            Environment::with_prefix("DUNE_CRAWLER")
+                .prefix_separator("__")
+                .separator("__"),
+        )
+    }
+
+    fn with_environment(
+        path: &Path,
+        environment: Option<&str>,
+        variables: Environment,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = Config::builder().add_source(File::from(path).required(true));
+
+        if let Some(environment) = environment {
+            let overlay = path
+                .with_file_name(environment)
+                .with_extension(path.extension().unwrap_or_default());
+            builder = builder.add_source(File::from(overlay).required(true));
+        }
+
+        let config = builder.add_source(variables).build()?;
+
+        let settings: Self = serde_path_to_error::deserialize(config)
+            .map_err(|err| anyhow!("invalid configuration at `{}`: {}", err.path(), err.inner()))?;
+
+        if settings.dune.backend() == BackendKind::Api
+            && matches!(settings.catalog.source, CatalogSource::ResultId(_))
+        {
+            bail!(
+                "invalid configuration at `catalog.source`: the api backend cannot load a result \
+                by id, use a `query_id` or a `file` source"
+            );
+        }
+
+        Ok(settings)
+    }
+
+    pub(crate) fn application(&self) -> &ApplicationSettings {
+        &self.application
+    }
+
+    pub(crate) fn database(&self) -> &DatabaseSettings {
+        &self.database
+    }
+
+    pub(crate) fn catalog(&self) -> &CatalogSettings {
+        &self.catalog
+    }
+
+    pub(crate) fn dune(&self) -> &DuneSettings {
+        &self.dune
+    }
+
+    pub(crate) fn retry(&self) -> &RetrySettings {
+        &self.retry
+    }
+
+    pub(crate) fn jobs(&self) -> &JobSettings {
+        &self.jobs
+    }
+}
+
+#[cfg(test)]
+fn write_config(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dune-crawler-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[cfg(test)]
+const BASE_CONFIG: &str = r#"
+application:
+  cookie: "csrf=base"
+  userid: 1
+database:
+  host: "127.0.0.1"
+  port: 5432
+  username: "postgres"
+  password: "postgres"
+  name: "eth"
+catalog:
+  source:
+    kind: query_id
+    value: 1
+"#;
+
+#[test]
+fn settings_should_apply_overlay_then_environment() {
+    let path = write_config("layered.yaml", BASE_CONFIG);
+    write_config(
+        "staging.yaml",
+        "database:\n  host: \"db.staging\"\n  port: 6432\n",
+    );
+
+    let variables = Environment::with_prefix("DUNE_CRAWLER")
+        .prefix_separator("__")
+        .separator("__")
+        .source(Some(
+            [(
+                String::from("DUNE_CRAWLER__DATABASE__PORT"),
+                String::from("7432"),
+            )]
+            .into(),
+        ));
+
+    let settings = Settings::with_environment(&path, Some("staging"), variables).unwrap();
+    assert_eq!(settings.database().host, "db.staging");
+    assert_eq!(settings.database().port, 7432);
+    assert_eq!(settings.database().name, "eth");
+}
+
+#[test]
+fn settings_should_switch_the_catalog_source_in_an_overlay() {
+    let path = write_config("source.yaml", BASE_CONFIG);
+    write_config(
+        "local.yaml",
+        "catalog:\n  source:\n    kind: file\n    value: \"catalog.csv\"\n",
+    );
+
+    let settings = Settings::with_environment(
+        &path,
+        Some("local"),
+        Environment::default().source(Some(Default::default())),
+    )
+    .unwrap();
+    assert!(
+        matches!(settings.catalog().source(), CatalogSource::File(path) if path == Path::new("catalog.csv"))
+    );
+
+    let variables = Environment::with_prefix("DUNE_CRAWLER")
+        .prefix_separator("__")
+        .separator("__")
+        .source(Some(
+            [
+                (
+                    String::from("DUNE_CRAWLER__CATALOG__SOURCE__KIND"),
+                    String::from("query_id"),
+                ),
+                (
+                    String::from("DUNE_CRAWLER__CATALOG__SOURCE__VALUE"),
+                    String::from("2371"),
+                ),
+            ]
+            .into(),
+        ));
+
+    let settings = Settings::with_environment(&path, Some("local"), variables).unwrap();
+    assert!(matches!(
+        settings.catalog().source(),
+        CatalogSource::QueryId(2371)
+    ));
+}
+
+#[test]
+fn settings_should_reject_a_result_id_with_the_api_backend() {
+    let path = write_config(
+        "api.yaml",
+        &BASE_CONFIG.replace(
+            "    kind: query_id\n    value: 1\n",
+            "    kind: result_id\n    value: \"887c3f39\"\ndune:\n  backend: api\n  engine: dune_sql\n",
+        ),
+    );
+
+    let err = Settings::with_environment(
+        &path,
+        None,
+        Environment::default().source(Some(Default::default())),
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("invalid configuration at `catalog.source`"));
+}
+
+#[test]
+fn settings_should_read_and_redact_the_cookie_file() {
+    let cookie = write_config("cookie.txt", "csrf=secret; auth-refresh=secret\n");
+    let path = write_config(
+        "cookie.yaml",
+        &BASE_CONFIG.replace(
+            "  cookie: \"csrf=base\"",
+            &format!("  cookie_file: {:?}", cookie.display()),
+        ),
+    );
+
+    let settings = Settings::with_environment(
+        &path,
+        None,
+        Environment::default().source(Some(Default::default())),
+    )
+    .unwrap();
+    assert_eq!(
+        settings.application().cookie().unwrap().expose_secret(),
+        "csrf=secret; auth-refresh=secret"
+    );
+    assert!(!format!("{:?}", settings).contains("secret;"));
+}
+
+#[test]
+fn settings_should_name_the_missing_key() {
+    let path = write_config(
+        "missing.yaml",
+        &BASE_CONFIG.replace("  host: \"127.0.0.1\"\n", ""),
+    );
+
+    let err = Settings::with_environment(
+        &path,
+        None,
+        Environment::default().source(Some(Default::default())),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid configuration at `database`: missing field `host`"
+    );
+}
+
+#[test]
+fn retry_delay_should_back_off_exponentially_up_to_the_cap() {
+    let retry = RetrySettings {
+        max_retries: 5,
+        initial_backoff_ms: 100,
+        max_backoff_ms: 1000,
+    };
+
+    for _ in 0..100 {
+        let first = retry.delay(1).as_millis();
+        assert!((50..=100).contains(&first), "{}", first);
+
+        let third = retry.delay(3).as_millis();
+        assert!((200..=400).contains(&third), "{}", third);
+
+        let capped = retry.delay(40).as_millis();
+        assert!((500..=1000).contains(&capped), "{}", capped);
+    }
+}
+
+#[test]
+fn job_poll_interval_should_double_up_to_the_cap() {
+    let jobs = JobSettings {
+        poll_interval_ms: 500,
+        max_poll_interval_ms: 3000,
+        max_wait_secs: 60,
+        ..JobSettings::default()
+    };
+
+    assert_eq!(jobs.poll_interval(1), Duration::from_millis(500));
+    assert_eq!(jobs.poll_interval(3), Duration::from_millis(2000));
+    assert_eq!(jobs.poll_interval(4), Duration::from_millis(3000));
+    assert_eq!(jobs.poll_interval(64), Duration::from_millis(3000));
+}
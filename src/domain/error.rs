@@ -0,0 +1,205 @@
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::QueryError;
+
+/// An entry of the `errors` of a GraphQL response.
+#[derive(Debug, Deserialize)]
+pub struct GraphQLError {
+    message: String,
+    extensions: Option<Value>,
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.extensions {
+            Some(ref extensions) => write!(f, "{} ({})", self.message, extensions),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl GraphQLError {
+    /// The `extensions.code` Hasura tags the error with, e.g. `invalid-jwt`.
+    fn code(&self) -> Option<&str> {
+        self.extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("code"))
+            .and_then(Value::as_str)
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQLErrors {
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+/// Everything that can go wrong talking to Dune.
+#[derive(Debug, Error)]
+pub enum DuneError {
+    #[error("request failed, {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected status {status}, {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("rate limited, retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("graphql errors, {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    GraphQL(Vec<GraphQLError>),
+    #[error("failed to decode {body}, {source}")]
+    Decode {
+        source: serde_json::Error,
+        body: String,
+    },
+    #[error("the Dune session has expired")]
+    SessionExpired,
+    #[error("job {job_id} failed, {error}")]
+    Sql { job_id: String, error: QueryError },
+    #[error("job {job_id} failed, {message}")]
+    QueryFailed { job_id: String, message: String },
+    #[error("job {job_id} was cancelled after running for {waited:?}")]
+    JobTimedOut { job_id: String, waited: Duration },
+    #[error("the {backend} backend cannot {action}")]
+    Unsupported {
+        backend: &'static str,
+        action: &'static str,
+    },
+}
+
+impl DuneError {
+    /// Whether sending the same request again may succeed: network failures, 5xx and 429.
+    /// SQL errors, GraphQL errors and undecodable responses would fail the same way again.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            DuneError::Http(err) => !err.is_builder(),
+            DuneError::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::REQUEST_TIMEOUT
+            }
+            DuneError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// How long Dune asked to wait before the next request.
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DuneError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Read the status and body of a response, turning a 429 into [`DuneError::RateLimited`].
+pub(crate) async fn read_response(response: Response) -> Result<(StatusCode, String), DuneError> {
+    let status = response.status();
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        return Err(DuneError::RateLimited { retry_after });
+    }
+
+    Ok((status, response.text().await?))
+}
+
+/// Whether a GraphQL response was rejected because the bearer token expired, told by the status
+/// or the error codes only, as the data of a response may hold any text.
+fn is_session_expired(status: StatusCode, errors: &[GraphQLError]) -> bool {
+    status == StatusCode::UNAUTHORIZED
+        || errors
+            .iter()
+            .any(|error| error.code() == Some("invalid-jwt"))
+}
+
+/// Decode a Dune response body, telling an expired session, an HTTP error and GraphQL `errors`
+/// apart from a body that is not a `T`.
+pub(crate) fn decode_response<T>(status: StatusCode, body: String) -> Result<T, DuneError>
+where
+    T: DeserializeOwned,
+{
+    let errors = serde_json::from_str::<GraphQLErrors>(&body)
+        .map(|response| response.errors)
+        .unwrap_or_default();
+
+    if is_session_expired(status, &errors) {
+        return Err(DuneError::SessionExpired);
+    }
+
+    if !status.is_success() {
+        return Err(DuneError::Status { status, body });
+    }
+
+    if !errors.is_empty() {
+        return Err(DuneError::GraphQL(errors));
+    }
+
+    serde_json::from_str(&body).map_err(|source| DuneError::Decode { source, body })
+}
+
+#[test]
+fn decode_response_should_classify_errors() {
+    let expired = r#"{"errors":[{"extensions":{"path":"$","code":"invalid-jwt"},"message":"Could not verify JWT: JWTExpired"}]}"#;
+    assert!(matches!(
+        decode_response::<Value>(StatusCode::OK, expired.to_owned()),
+        Err(DuneError::SessionExpired)
+    ));
+
+    let data =
+        r#"{"data":{"get_result_by_job_id":[{"data":{"label_name":"invalid-jwt JWTExpired"}}]}}"#;
+    assert!(decode_response::<Value>(StatusCode::OK, data.to_owned()).is_ok());
+
+    assert!(matches!(
+        decode_response::<Value>(StatusCode::BAD_GATEWAY, String::from("<html>")),
+        Err(DuneError::Status {
+            status: StatusCode::BAD_GATEWAY,
+            ..
+        })
+    ));
+
+    let errors = r#"{"errors":[{"extensions":{"path":"$.selectionSet","code":"validation-failed"},"message":"field \"foo\" not found"}]}"#;
+    assert!(matches!(
+        decode_response::<Value>(StatusCode::OK, errors.to_owned()),
+        Err(DuneError::GraphQL(errors)) if errors.len() == 1
+    ));
+
+    match decode_response::<Vec<i32>>(StatusCode::OK, String::from(r#"{"data":{}}"#)) {
+        Err(DuneError::Decode { body, .. }) => assert_eq!(body, r#"{"data":{}}"#),
+        _ => panic!("expected a decode error"),
+    }
+}
+
+#[test]
+fn dune_error_should_tell_transient_from_permanent() {
+    assert!(DuneError::Status {
+        status: StatusCode::BAD_GATEWAY,
+        body: String::new(),
+    }
+    .is_transient());
+    assert!(DuneError::RateLimited {
+        retry_after: Some(Duration::from_secs(3)),
+    }
+    .is_transient());
+    assert!(!DuneError::Status {
+        status: StatusCode::BAD_REQUEST,
+        body: String::new(),
+    }
+    .is_transient());
+    assert!(!DuneError::SessionExpired.is_transient());
+    assert!(!DuneError::QueryFailed {
+        job_id: String::new(),
+        message: String::new(),
+    }
+    .is_transient());
+}
@@ -1,5 +1,9 @@
+mod error;
 mod model;
 mod query;
 
+pub(crate) use error::*;
+pub use error::{DuneError, GraphQLError};
 pub(crate) use model::*;
+pub use query::QueryError;
 pub(crate) use query::*;
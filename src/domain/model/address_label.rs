@@ -1,22 +1,144 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Deserialize, Serialize)]
-pub(crate) struct AddressLabel {
-    address: String,
-    label_type: String,
-    label_name: String,
-}
-
-impl AddressLabel {
-    pub(crate) fn address(&self) -> String {
-        self.address.replace('\\', "0")
-    }
-
-    pub(crate) fn label_type(&self) -> &str {
-        &self.label_type
-    }
-
-    pub(crate) fn label_name(&self) -> &str {
-        &self.label_name
-    }
-}
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct AddressLabel {
+    blockchain: String,
+    address: String,
+    label_type: String,
+    label_name: String,
+    #[serde(flatten)]
+    details: LabelDetails,
+}
+
+/// What the Spellbook labels tables tell about a label besides its name, all missing on the legacy
+/// engine.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LabelDetails {
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    contributor: Option<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    model_name: Option<String>,
+}
+
+/// Dune writes timestamps as `2023-02-14 10:01:57.000 UTC`, RFC 3339 is taken too.
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = match Option::<String>::deserialize(deserializer)? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(&value) {
+        return Ok(Some(timestamp.with_timezone(&Utc)));
+    }
+
+    NaiveDateTime::parse_from_str(value.trim_end_matches(" UTC"), "%Y-%m-%d %H:%M:%S%.f")
+        .map(|timestamp| Some(Utc.from_utc_datetime(&timestamp)))
+        .map_err(|err| de::Error::custom(format!("invalid timestamp {:?}, {}", value, err)))
+}
+
+impl AddressLabel {
+    pub(crate) fn blockchain(&self) -> &str {
+        &self.blockchain
+    }
+
+    pub(crate) fn address(&self) -> String {
+        self.address.replace('\\', "0")
+    }
+
+    pub(crate) fn label_type(&self) -> &str {
+        &self.label_type
+    }
+
+    pub(crate) fn label_name(&self) -> &str {
+        &self.label_name
+    }
+
+    pub(crate) fn details(&self) -> &LabelDetails {
+        &self.details
+    }
+}
+
+impl LabelDetails {
+    /// What the legacy labels table calls the `type` of a label, unlike the Spellbook `label_type`.
+    pub(crate) fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    /// Who added the label to Spellbook.
+    pub(crate) fn contributor(&self) -> Option<&str> {
+        self.contributor.as_deref()
+    }
+
+    /// How the label was found, e.g. `query` or `static`.
+    pub(crate) fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub(crate) fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    pub(crate) fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// The Spellbook model the label comes from.
+    pub(crate) fn model_name(&self) -> Option<&str> {
+        self.model_name.as_deref()
+    }
+}
+
+#[test]
+fn deserialize_spellbook_label_should_keep_its_details() {
+    let json = r#"
+    {
+        "blockchain": "ethereum",
+        "address": "0x00000000219ab540356cbb839cbe05303d7705fa",
+        "label_name": "Eth2 Deposit Contract",
+        "label_type": "identifier",
+        "category": "infrastructure",
+        "contributor": "hildobby",
+        "source": "static",
+        "created_at": "2022-10-05 00:00:00.000 UTC",
+        "updated_at": "2023-02-14T10:01:57.123Z",
+        "model_name": "eth2"
+    }
+    "#;
+
+    let label = serde_json::from_str::<AddressLabel>(json).unwrap();
+    let details = label.details();
+    assert_eq!(label.label_type(), "identifier");
+    assert_eq!(details.category(), Some("infrastructure"));
+    assert_eq!(details.contributor(), Some("hildobby"));
+    assert_eq!(details.model_name(), Some("eth2"));
+    assert_eq!(
+        details.created_at().unwrap().to_rfc3339(),
+        "2022-10-05T00:00:00+00:00"
+    );
+    assert_eq!(
+        details.updated_at().unwrap().timestamp_millis(),
+        1676368917123
+    );
+}
+
+#[test]
+fn deserialize_legacy_label_should_have_no_details() {
+    let json = r#"{"blockchain": "ethereum", "address": "\\x00ff", "label_name": "x", "label_type": "dao"}"#;
+
+    let label = serde_json::from_str::<AddressLabel>(json).unwrap();
+    assert_eq!(label.address(), "0x00ff");
+    assert!(label.details().category().is_none());
+    assert!(label.details().updated_at().is_none());
+}
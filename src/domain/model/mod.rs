@@ -1,3 +1,5 @@
 mod address_label;
+mod parameter;
 
 pub(crate) use address_label::*;
+pub(crate) use parameter::*;
@@ -0,0 +1,100 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// A `{{key}}` parameter of a saved Dune query, which Dune pastes into the SQL as it is.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Parameter {
+    key: String,
+    #[serde(flatten)]
+    value: ParameterValue,
+}
+
+/// Dune sends every parameter value as a string, numbers and datetimes (`2022-06-17 00:00:00`)
+/// included.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ParameterValue {
+    Text {
+        value: String,
+    },
+    Number {
+        value: String,
+    },
+    Enum {
+        value: String,
+        #[serde(rename = "enumOptions", default)]
+        options: Vec<String>,
+    },
+    Datetime {
+        value: String,
+    },
+}
+
+impl Parameter {
+    pub(crate) fn text(key: &str, value: impl Into<String>) -> Self {
+        Self {
+            key: key.to_owned(),
+            value: ParameterValue::Text {
+                value: value.into(),
+            },
+        }
+    }
+
+    pub(crate) fn number(key: &str, value: impl Display) -> Self {
+        Self {
+            key: key.to_owned(),
+            value: ParameterValue::Number {
+                value: value.to_string(),
+            },
+        }
+    }
+
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn value(&self) -> &str {
+        match self.value {
+            ParameterValue::Text { ref value }
+            | ParameterValue::Number { ref value }
+            | ParameterValue::Enum { ref value, .. }
+            | ParameterValue::Datetime { ref value } => value,
+        }
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value())
+    }
+}
+
+#[test]
+fn serialize_parameter_should_ok() {
+    assert_eq!(
+        serde_json::to_string(&[
+            Parameter::text("label_name", "'Ol'' Bob'"),
+            Parameter::number("limit", 10)
+        ])
+        .unwrap(),
+        r#"[{"key":"label_name","type":"text","value":"'Ol'' Bob'"},{"key":"limit","type":"number","value":"10"}]"#
+    );
+}
+
+#[test]
+fn deserialize_parameter_should_ok() {
+    let json = r#"
+    [
+        {"key": "chain", "type": "enum", "value": "ethereum", "enumOptions": ["ethereum", "gnosis"]},
+        {"key": "since", "type": "datetime", "value": "2022-06-17 00:00:00"}
+    ]
+    "#;
+
+    let parameters = serde_json::from_str::<Vec<Parameter>>(json).unwrap();
+    assert!(matches!(
+        parameters[0].value,
+        ParameterValue::Enum { ref options, .. } if options.len() == 2
+    ));
+    assert_eq!(parameters[1].to_string(), "since=2022-06-17 00:00:00");
+}
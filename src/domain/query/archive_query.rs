@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use super::GraphQLOperation;
+
+#[derive(Serialize)]
+pub(crate) struct QueryIdVariables {
+    id: i32,
+}
+
+pub(crate) struct ArchiveQuery {
+    variables: QueryIdVariables,
+}
+
+impl ArchiveQuery {
+    pub(crate) fn new(id: i32) -> Self {
+        Self {
+            variables: QueryIdVariables { id },
+        }
+    }
+}
+
+impl GraphQLOperation for ArchiveQuery {
+    const NAME: &'static str = "ArchiveQuery";
+    const QUERY: &'static str = "mutation ArchiveQuery($id: Int!) {\n  update_queries_by_pk(pk_columns: {id: $id}, _set: {is_archived: true}) {\n    id\n    is_archived\n    __typename\n  }\n}\n";
+
+    type Variables = QueryIdVariables;
+    type Response = ArchiveQueryResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+pub(crate) struct DeleteQuery {
+    variables: QueryIdVariables,
+}
+
+impl DeleteQuery {
+    pub(crate) fn new(id: i32) -> Self {
+        Self {
+            variables: QueryIdVariables { id },
+        }
+    }
+}
+
+impl GraphQLOperation for DeleteQuery {
+    const NAME: &'static str = "DeleteQuery";
+    const QUERY: &'static str = "mutation DeleteQuery($id: Int!) {\n  delete_queries_by_pk(id: $id) {\n    id\n    __typename\n  }\n}\n";
+
+    type Variables = QueryIdVariables;
+    type Response = ArchiveQueryResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryId {
+    id: i32,
+}
+
+#[derive(Deserialize)]
+struct ArchiveQueryResponseData {
+    #[serde(alias = "delete_queries_by_pk")]
+    update_queries_by_pk: Option<QueryId>,
+}
+
+/// The response to both [`ArchiveQuery`] and [`DeleteQuery`].
+#[derive(Deserialize)]
+pub(crate) struct ArchiveQueryResponse {
+    data: ArchiveQueryResponseData,
+}
+
+impl ArchiveQueryResponse {
+    /// The archived or deleted query, missing when there was no such query.
+    pub(crate) fn query_id(&self) -> Option<i32> {
+        self.data
+            .update_queries_by_pk
+            .as_ref()
+            .map(|query| query.id)
+    }
+}
+
+#[test]
+fn deserialize_archive_query_response_should_ok() {
+    let json = r#"
+    {
+        "data": {
+            "update_queries_by_pk": {"id": 917003, "is_archived": true, "__typename": "queries"}
+        }
+    }
+    "#;
+    let res = serde_json::from_str::<ArchiveQueryResponse>(json).unwrap();
+    assert_eq!(res.query_id(), Some(917003));
+
+    let json = r#"{"data": {"delete_queries_by_pk": null}}"#;
+    let res = serde_json::from_str::<ArchiveQueryResponse>(json).unwrap();
+    assert_eq!(res.query_id(), None);
+}
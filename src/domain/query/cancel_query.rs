@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use super::GraphQLOperation;
+
+#[derive(Serialize)]
+pub(crate) struct CancelQueryVariables {
+    job_id: String,
+}
+
+impl CancelQueryVariables {
+    fn new(job_id: String) -> Self {
+        Self { job_id }
+    }
+}
+
+pub(crate) struct CancelQuery {
+    variables: CancelQueryVariables,
+}
+
+impl CancelQuery {
+    pub(crate) fn new(job_id: String) -> Self {
+        Self {
+            variables: CancelQueryVariables::new(job_id),
+        }
+    }
+}
+
+impl GraphQLOperation for CancelQuery {
+    const NAME: &'static str = "CancelQuery";
+    const QUERY: &'static str = "mutation CancelQuery($job_id: uuid!) {\n  cancel_query(job_id: $job_id) {\n    job_id\n    __typename\n  }\n}\n";
+
+    type Variables = CancelQueryVariables;
+    type Response = CancelQueryResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Deserialize)]
+struct CancelQueryResponseJob {
+    job_id: String,
+}
+
+#[derive(Deserialize)]
+struct CancelQueryResponseData {
+    cancel_query: Option<CancelQueryResponseJob>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CancelQueryResponse {
+    data: CancelQueryResponseData,
+}
+
+impl CancelQueryResponse {
+    /// The cancelled job, missing when it had already finished.
+    pub(crate) fn job_id(&self) -> Option<&str> {
+        self.data
+            .cancel_query
+            .as_ref()
+            .map(|job| job.job_id.as_str())
+    }
+}
+
+#[test]
+fn deserialize_cancel_query_response_should_ok() {
+    let json = r#"
+    {
+        "data": {
+            "cancel_query": {
+                "job_id": "b0a5808d-a4f7-402e-b7e5-d6db9c0d0913",
+                "__typename": "cancel_query_response"
+            }
+        }
+    }
+    "#;
+
+    let res = serde_json::from_str::<CancelQueryResponse>(json).unwrap();
+    assert_eq!(res.job_id(), Some("b0a5808d-a4f7-402e-b7e5-d6db9c0d0913"));
+}
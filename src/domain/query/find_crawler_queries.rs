@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{GraphQLOperation, CRAWLER_QUERY_NAME, CRAWLER_QUERY_TAG};
+
+#[derive(Serialize)]
+pub(crate) struct FindCrawlerQueriesVariables {
+    user_id: i32,
+    name: &'static str,
+    tags: [&'static str; 1],
+    archived: Vec<bool>,
+}
+
+/// The queries of `user_id` the crawler saved, found by their name or tag.
+pub(crate) struct FindCrawlerQueries {
+    variables: FindCrawlerQueriesVariables,
+}
+
+impl FindCrawlerQueries {
+    pub(crate) fn new(user_id: i32, include_archived: bool) -> Self {
+        Self {
+            variables: FindCrawlerQueriesVariables {
+                user_id,
+                name: CRAWLER_QUERY_NAME,
+                tags: [CRAWLER_QUERY_TAG],
+                archived: if include_archived {
+                    vec![false, true]
+                } else {
+                    vec![false]
+                },
+            },
+        }
+    }
+}
+
+impl GraphQLOperation for FindCrawlerQueries {
+    const NAME: &'static str = "FindCrawlerQueries";
+    const QUERY: &'static str = "query FindCrawlerQueries($user_id: Int!, $name: String!, $tags: jsonb!, $archived: [Boolean!]!) {\n  queries(where: {user_id: {_eq: $user_id}, is_archived: {_in: $archived}, _or: [{name: {_eq: $name}}, {tags: {_contains: $tags}}]}, order_by: {id: asc}) {\n    id\n    name\n    created_at\n    is_archived\n    __typename\n  }\n}\n";
+
+    type Variables = FindCrawlerQueriesVariables;
+    type Response = FindCrawlerQueriesResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CrawlerQuery {
+    id: i32,
+    name: String,
+    created_at: DateTime<Utc>,
+    is_archived: bool,
+}
+
+impl CrawlerQuery {
+    pub(crate) fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub(crate) fn is_archived(&self) -> bool {
+        self.is_archived
+    }
+}
+
+#[derive(Deserialize)]
+struct FindCrawlerQueriesResponseData {
+    queries: Vec<CrawlerQuery>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FindCrawlerQueriesResponse {
+    data: FindCrawlerQueriesResponseData,
+}
+
+impl FindCrawlerQueriesResponse {
+    pub(crate) fn queries(self) -> Vec<CrawlerQuery> {
+        self.data.queries
+    }
+}
+
+#[test]
+fn deserialize_find_crawler_queries_response_should_ok() {
+    let json = r#"
+    {
+        "data": {
+            "queries": [
+                {
+                    "id": 917003,
+                    "name": "dune-crawler labels",
+                    "created_at": "2022-06-17T01:46:01.596472+00:00",
+                    "is_archived": false,
+                    "__typename": "queries"
+                }
+            ]
+        }
+    }
+    "#;
+
+    let queries = serde_json::from_str::<FindCrawlerQueriesResponse>(json)
+        .unwrap()
+        .queries();
+    assert_eq!(queries[0].id(), 917003);
+    assert_eq!(queries[0].name(), CRAWLER_QUERY_NAME);
+    assert!(!queries[0].is_archived());
+    assert_eq!(queries[0].created_at().timestamp(), 1655430361);
+}
@@ -1,122 +1,228 @@
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
-
-#[derive(Serialize)]
-struct FindResultDataByJobIdVariables {
-    job_id: String,
-}
-
-impl FindResultDataByJobIdVariables {
-    fn new(job_id: String) -> Self {
-        Self { job_id }
-    }
-}
-
-#[derive(Serialize)]
-pub(crate) struct FindResultDataByJobId {
-    #[serde(rename = "operationName")]
-    operation_name: &'static str,
-    variables: FindResultDataByJobIdVariables,
-    query: &'static str,
-}
-
-impl FindResultDataByJobId {
-    pub(crate) fn new(job_id: String) -> Self {
-        Self {
-            operation_name: "FindResultDataByJob",
-            variables: FindResultDataByJobIdVariables::new(job_id),
-            query: "query FindResultDataByJob($job_id: uuid!) {\n  query_results(where: {job_id: {_eq: $job_id}, error: {_is_null: true}}) {\n    id\n    job_id\n    runtime\n    generated_at\n    columns\n    __typename\n  }\n  query_errors(where: {job_id: {_eq: $job_id}}) {\n    id\n    job_id\n    runtime\n    message\n    metadata\n    type\n    generated_at\n    __typename\n  }\n  get_result_by_job_id(args: {want_job_id: $job_id}) {\n    data\n    __typename\n  }\n}\n",
-        }
-    }
-}
-
-#[derive(Serialize)]
-struct FindResultDataByResultIdVariables {
-    result_id: String,
-    error_id: &'static str,
-}
-
-impl FindResultDataByResultIdVariables {
-    fn new(result_id: String) -> Self {
-        Self {
-            result_id,
-            error_id: "00000000-0000-0000-0000-000000000000",
-        }
-    }
-}
-
-#[derive(Serialize)]
-pub(crate) struct FindResultDataByResultId {
-    #[serde(rename = "operationName")]
-    operation_name: &'static str,
-    variables: FindResultDataByResultIdVariables,
-    query: &'static str,
-}
-
-impl FindResultDataByResultId {
-    pub(crate) fn new(result_id: String) -> Self {
-        Self {
-            operation_name: "FindResultDataByResult",
-            variables: FindResultDataByResultIdVariables::new(result_id),
-            query: "query FindResultDataByResult($result_id: uuid!, $error_id: uuid!) {\n  query_results(where: {id: {_eq: $result_id}}) {\n    id\n    job_id\n    runtime\n    generated_at\n    columns\n    __typename\n  }\n  query_errors(where: {id: {_eq: $error_id}}) {\n    id\n    job_id\n    runtime\n    message\n    metadata\n    type\n    generated_at\n    __typename\n  }\n  get_result_by_result_id(args: {want_result_id: $result_id}) {\n    data\n    __typename\n  }\n}\n",
-        }
-    }
-}
-
-#[derive(Debug, Deserialize)]
-pub(crate) struct FindResult<T> {
-    data: T,
-}
-
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum FindResultsData<T> {
-    ByJob {
-        get_result_by_job_id: Vec<FindResult<T>>,
-    },
-    ByResult {
-        get_result_by_result_id: Vec<FindResult<T>>,
-    },
-}
-
-#[derive(Deserialize)]
-pub(crate) struct FindResultDataResponse<T> {
-    data: FindResultsData<T>,
-}
-
-impl<T> FindResultDataResponse<T>
-where
-    T: DeserializeOwned,
-{
-    pub(crate) fn data(self) -> Vec<T> {
-        match self.data {
-            FindResultsData::ByJob {
-                get_result_by_job_id,
-            } => get_result_by_job_id
-                .into_iter()
-                .map(|result| result.data)
-                .collect(),
-            FindResultsData::ByResult {
-                get_result_by_result_id,
-            } => get_result_by_result_id
-                .into_iter()
-                .map(|result| result.data)
-                .collect(),
-        }
-    }
-}
-
-#[test]
-fn deserialize_find_result_data_response_should_ok() {
-    use serde_json::Value;
-
-    let json = r#"
-    {
-        "data": {
-          "query_results": [],
-          "get_result_by_result_id": []
-        }
-      }
-    "#;
-
-    assert!(serde_json::from_str::<FindResultDataResponse<Value>>(&json).is_ok());
-}
+use std::{
+    fmt::{Display, Formatter},
+    marker::PhantomData,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use super::GraphQLOperation;
+
+#[derive(Serialize)]
+pub(crate) struct FindResultDataByJobIdVariables {
+    job_id: String,
+}
+
+impl FindResultDataByJobIdVariables {
+    fn new(job_id: String) -> Self {
+        Self { job_id }
+    }
+}
+
+pub(crate) struct FindResultDataByJobId<T> {
+    variables: FindResultDataByJobIdVariables,
+    rows: PhantomData<fn() -> T>,
+}
+
+impl<T> FindResultDataByJobId<T> {
+    pub(crate) fn new(job_id: String) -> Self {
+        Self {
+            variables: FindResultDataByJobIdVariables::new(job_id),
+            rows: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> GraphQLOperation for FindResultDataByJobId<T> {
+    const NAME: &'static str = "FindResultDataByJob";
+    const QUERY: &'static str = "query FindResultDataByJob($job_id: uuid!) {\n  query_results(where: {job_id: {_eq: $job_id}, error: {_is_null: true}}) {\n    id\n    job_id\n    runtime\n    generated_at\n    columns\n    __typename\n  }\n  query_errors(where: {job_id: {_eq: $job_id}}) {\n    id\n    job_id\n    runtime\n    message\n    metadata\n    type\n    generated_at\n    __typename\n  }\n  get_result_by_job_id(args: {want_job_id: $job_id}) {\n    data\n    __typename\n  }\n}\n";
+
+    type Variables = FindResultDataByJobIdVariables;
+    type Response = FindResultDataResponse<T>;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct FindResultDataByResultIdVariables {
+    result_id: String,
+    error_id: &'static str,
+}
+
+impl FindResultDataByResultIdVariables {
+    fn new(result_id: String) -> Self {
+        Self {
+            result_id,
+            error_id: "00000000-0000-0000-0000-000000000000",
+        }
+    }
+}
+
+pub(crate) struct FindResultDataByResultId<T> {
+    variables: FindResultDataByResultIdVariables,
+    rows: PhantomData<fn() -> T>,
+}
+
+impl<T> FindResultDataByResultId<T> {
+    pub(crate) fn new(result_id: String) -> Self {
+        Self {
+            variables: FindResultDataByResultIdVariables::new(result_id),
+            rows: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> GraphQLOperation for FindResultDataByResultId<T> {
+    const NAME: &'static str = "FindResultDataByResult";
+    const QUERY: &'static str = "query FindResultDataByResult($result_id: uuid!, $error_id: uuid!) {\n  query_results(where: {id: {_eq: $result_id}}) {\n    id\n    job_id\n    runtime\n    generated_at\n    columns\n    __typename\n  }\n  query_errors(where: {id: {_eq: $error_id}}) {\n    id\n    job_id\n    runtime\n    message\n    metadata\n    type\n    generated_at\n    __typename\n  }\n  get_result_by_result_id(args: {want_result_id: $result_id}) {\n    data\n    __typename\n  }\n}\n";
+
+    type Variables = FindResultDataByResultIdVariables;
+    type Response = FindResultDataResponse<T>;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FindResult<T> {
+    data: T,
+}
+
+/// The `query_results` row of a successful job.
+#[derive(Debug, Deserialize)]
+pub(crate) struct QueryResult {
+    runtime: Option<f64>,
+    generated_at: String,
+    #[serde(default)]
+    columns: Vec<String>,
+}
+
+impl QueryResult {
+    pub(crate) fn runtime(&self) -> Option<f64> {
+        self.runtime
+    }
+
+    pub(crate) fn generated_at(&self) -> &str {
+        &self.generated_at
+    }
+
+    pub(crate) fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+/// A `query_errors` row, what Dune reports when the SQL of a job fails.
+#[derive(Debug, Deserialize)]
+pub struct QueryError {
+    message: String,
+    metadata: Option<Value>,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} error, {}",
+            self.error_type.as_deref().unwrap_or("unknown"),
+            self.message
+        )?;
+
+        match self.metadata {
+            Some(ref metadata) if !metadata.is_null() => write!(f, " ({})", metadata),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FindResultsData<T> {
+    #[serde(default)]
+    query_results: Vec<QueryResult>,
+    #[serde(default)]
+    query_errors: Vec<QueryError>,
+    #[serde(
+        rename = "get_result_by_job_id",
+        alias = "get_result_by_result_id",
+        default = "Vec::new"
+    )]
+    results: Vec<FindResult<T>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FindResultDataResponse<T> {
+    data: FindResultsData<T>,
+}
+
+impl<T> FindResultDataResponse<T>
+where
+    T: DeserializeOwned,
+{
+    /// The result row, missing when the job failed or the result expired.
+    pub(crate) fn query_result(&self) -> Option<&QueryResult> {
+        self.data.query_results.first()
+    }
+
+    /// The first error Dune reported for the job.
+    pub(crate) fn take_query_error(&mut self) -> Option<QueryError> {
+        (!self.data.query_errors.is_empty()).then(|| self.data.query_errors.swap_remove(0))
+    }
+
+    pub(crate) fn data(self) -> Vec<T> {
+        self.data
+            .results
+            .into_iter()
+            .map(|result| result.data)
+            .collect()
+    }
+}
+
+#[test]
+fn deserialize_find_result_data_response_should_ok() {
+    let json = r#"
+    {
+        "data": {
+          "query_results": [],
+          "get_result_by_result_id": []
+        }
+      }
+    "#;
+
+    assert!(serde_json::from_str::<FindResultDataResponse<Value>>(json).is_ok());
+}
+
+#[test]
+fn deserialize_find_result_data_by_job_with_query_errors_should_ok() {
+    let json = r#"
+    {
+        "data": {
+          "query_results": [],
+          "query_errors": [
+            {
+              "id": "1f0ad2b8-75d8-4f4a-9e64-3d0d8b1ab5d4",
+              "job_id": "ae3b0c14-4022-426b-87ab-82048135d22c",
+              "runtime": 0,
+              "message": "syntax error at or near \"Bob\"",
+              "metadata": {"line": 1, "column": 98, "hint": ""},
+              "type": "syntax",
+              "generated_at": "2022-06-16T13:01:05.593305+00:00",
+              "__typename": "query_errors"
+            }
+          ],
+          "get_result_by_job_id": []
+        }
+      }
+    "#;
+
+    let mut res = serde_json::from_str::<FindResultDataResponse<Value>>(json).unwrap();
+    assert!(res.query_result().is_none());
+    assert_eq!(
+        res.take_query_error().unwrap().to_string(),
+        r#"syntax error, syntax error at or near "Bob" ({"column":98,"hint":"","line":1})"#
+    );
+    assert!(res.data().is_empty());
+}
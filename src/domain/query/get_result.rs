@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::GraphQLOperation;
+
+#[derive(Serialize)]
+pub(crate) struct GetResultVariables {
+    query_id: i32,
+    parameters: Vec<Value>,
+}
+
+impl GetResultVariables {
+    fn new(query_id: i32) -> Self {
+        Self {
+            query_id,
+            parameters: vec![],
+        }
+    }
+}
+
+pub(crate) struct GetResult {
+    variables: GetResultVariables,
+}
+
+impl GetResult {
+    pub(crate) fn new(query_id: i32) -> Self {
+        Self {
+            variables: GetResultVariables::new(query_id),
+        }
+    }
+}
+
+impl GraphQLOperation for GetResult {
+    const NAME: &'static str = "GetResult";
+    const QUERY: &'static str = "query GetResult($query_id: Int!, $parameters: [Parameter!]) {\n  get_result_v2(query_id: $query_id, parameters: $parameters) {\n    job_id\n    result_id\n    error_id\n    __typename\n  }\n}\n";
+
+    type Variables = GetResultVariables;
+    type Response = GetResultResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
+#[derive(Deserialize)]
+struct GetResultResponseResult {
+    result_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GetResultResponseData {
+    get_result_v2: GetResultResponseResult,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetResultResponse {
+    data: GetResultResponseData,
+}
+
+impl GetResultResponse {
+    pub(crate) fn result_id(self) -> Option<String> {
+        self.data.get_result_v2.result_id
+    }
+}
+
+#[test]
+fn deserialize_get_result_response_should_ok() {
+    let json = r#"
+    {
+        "data": {
+            "get_result_v2": {
+                "job_id": null,
+                "result_id": "887c3f39-89cb-4f1b-92fc-98c22dc02f2b",
+                "error_id": null,
+                "__typename": "get_result_response"
+            }
+        }
+    }
+    "#;
+
+    let res = serde_json::from_str::<GetResultResponse>(json).unwrap();
+    assert_eq!(
+        res.result_id().as_deref(),
+        Some("887c3f39-89cb-4f1b-92fc-98c22dc02f2b")
+    );
+}
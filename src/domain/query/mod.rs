@@ -1,9 +1,22 @@
-mod execute_query;
-mod find_results;
-mod get_queue_position;
-mod upsert_query;
-
-pub(crate) use execute_query::*;
-pub(crate) use find_results::*;
-pub(crate) use get_queue_position::*;
-pub(crate) use upsert_query::*;
+mod archive_query;
+mod cancel_query;
+mod execute_query;
+mod find_crawler_queries;
+mod find_results;
+mod get_queue_position;
+mod get_result;
+mod operation;
+mod session;
+mod upsert_query;
+
+pub(crate) use archive_query::*;
+pub(crate) use cancel_query::*;
+pub(crate) use execute_query::*;
+pub(crate) use find_crawler_queries::*;
+pub use find_results::QueryError;
+pub(crate) use find_results::*;
+pub(crate) use get_queue_position::*;
+pub(crate) use get_result::*;
+pub(crate) use operation::*;
+pub(crate) use session::*;
+pub(crate) use upsert_query::*;
@@ -0,0 +1,68 @@
+use std::future::Future;
+
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::domain::{decode_response, read_response, DuneError};
+
+/// A Dune GraphQL query or mutation, and what it is answered with.
+pub(crate) trait GraphQLOperation: Sync {
+    /// The `operationName` of the request.
+    const NAME: &'static str;
+    /// The GraphQL document.
+    const QUERY: &'static str;
+
+    type Variables: Serialize + Sync;
+    type Response: DeserializeOwned;
+
+    fn variables(&self) -> &Self::Variables;
+
+    /// Post the operation to `url`, with `token` as bearer token when there is one, and decode the
+    /// response, failing on its GraphQL `errors`.
+    fn execute<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a str,
+        token: Option<&'a str>,
+    ) -> impl Future<Output = Result<Self::Response, DuneError>> + Send + 'a {
+        async move {
+            let mut request = client.post(url).json(&GraphQLRequest {
+                operation_name: Self::NAME,
+                variables: self.variables(),
+                query: Self::QUERY,
+            });
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+
+            let (status, body) = read_response(request.send().await?).await?;
+            decode_response(status, body)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GraphQLRequest<'a, V> {
+    #[serde(rename = "operationName")]
+    operation_name: &'static str,
+    variables: &'a V,
+    query: &'static str,
+}
+
+#[test]
+fn graphql_request_should_carry_the_operation() {
+    let operation = super::CancelQuery::new(String::from("job-1"));
+    let request = GraphQLRequest {
+        operation_name: super::CancelQuery::NAME,
+        variables: operation.variables(),
+        query: super::CancelQuery::QUERY,
+    };
+
+    let json = serde_json::to_value(&request).unwrap();
+    assert_eq!(json["operationName"], "CancelQuery");
+    assert_eq!(json["variables"], serde_json::json!({"job_id": "job-1"}));
+    assert!(json["query"]
+        .as_str()
+        .unwrap()
+        .starts_with("mutation CancelQuery"));
+}
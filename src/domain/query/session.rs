@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(crate) struct SessionResponse {
+    token: String,
+}
+
+impl SessionResponse {
+    pub(crate) fn token(self) -> String {
+        self.token
+    }
+}
@@ -2,6 +2,16 @@ use serde::{Deserialize, Serialize};
 
 use serde_json::{Map, Value};
 
+use super::GraphQLOperation;
+use crate::domain::Parameter;
+
+/// The name the label query of a crawl goes by.
+pub(crate) const CRAWLER_QUERY_NAME: &str = "dune-crawler labels";
+/// The name the queries [`DuneClient::run_sql`](crate::DuneClient::run_sql) saves go by.
+pub(crate) const SQL_QUERY_NAME: &str = "dune-crawler sql";
+/// The tag every query the crawler saves carries, so leftovers can be found and cleaned up.
+pub(crate) const CRAWLER_QUERY_TAG: &str = "dune-crawler";
+
 #[derive(Serialize)]
 struct UpsertQueryVisualizationsData {
     r#type: &'static str,
@@ -61,24 +71,32 @@ struct UpsertQueryVariablesObject {
     is_archived: bool,
     is_temp: bool,
     is_private: bool,
-    parameters: Vec<Value>,
+    tags: Vec<&'static str>,
+    parameters: Vec<Parameter>,
     visualizations: UpsertQueryVisualizations,
 }
 
 impl UpsertQueryVariablesObject {
-    fn new(user_id: i32, query: String) -> Self {
+    fn new(
+        user_id: i32,
+        dataset_id: i32,
+        name: &'static str,
+        query: String,
+        parameters: Vec<Parameter>,
+    ) -> Self {
         Self {
             user_id,
+            name,
             query,
             schedule: None,
-            dataset_id: 4,
-            name: "NewQuery",
+            dataset_id,
             team_id: None,
             description: "",
             is_archived: false,
             is_temp: true,
             is_private: false,
-            parameters: vec![],
+            tags: vec![CRAWLER_QUERY_TAG],
+            parameters,
             visualizations: UpsertQueryVisualizations::default(),
         }
     }
@@ -100,7 +118,7 @@ impl Default for UpsertQueryOnConflict {
 }
 
 #[derive(Serialize)]
-struct UpsertQueryVariables {
+pub(crate) struct UpsertQueryVariables {
     favs_last_24h: bool,
     favs_last_7d: bool,
     favs_last_30d: bool,
@@ -111,41 +129,67 @@ struct UpsertQueryVariables {
 }
 
 impl UpsertQueryVariables {
-    fn new(user_id: i32, session_id: i32, query: String) -> Self {
+    fn new(
+        user_id: i32,
+        session_id: i32,
+        dataset_id: i32,
+        name: &'static str,
+        query: String,
+        parameters: Vec<Parameter>,
+    ) -> Self {
         Self {
             session_id,
             favs_last_24h: false,
             favs_last_7d: false,
             favs_last_30d: false,
             favs_all_time: true,
-            object: UpsertQueryVariablesObject::new(user_id, query),
+            object: UpsertQueryVariablesObject::new(user_id, dataset_id, name, query, parameters),
             on_conflict: UpsertQueryOnConflict::default(),
         }
     }
 }
 
-#[derive(Serialize)]
 pub(crate) struct UpsertQuery {
-    #[serde(rename = "operationName")]
-    operation_name: String,
     variables: UpsertQueryVariables,
-    query: &'static str,
 }
 
 impl UpsertQuery {
-    pub(crate) fn new(user_id: i32, session_id: i32, query: String) -> Self {
+    /// Save `query` as `name` for the dataset `dataset_id`, its `{{key}}` placeholders defaulting
+    /// to `parameters`.
+    pub(crate) fn new(
+        user_id: i32,
+        session_id: i32,
+        dataset_id: i32,
+        name: &'static str,
+        query: String,
+        parameters: Vec<Parameter>,
+    ) -> Self {
         Self {
-            operation_name: String::from("UpsertQuery"),
-            variables: UpsertQueryVariables::new(user_id, session_id, query),
-            query: r#"mutation UpsertQuery($session_id: Int!, $object: queries_insert_input!, $on_conflict: queries_on_conflict!, $favs_last_24h: Boolean! = false, $favs_last_7d: Boolean! = false, $favs_last_30d: Boolean! = false, $favs_all_time: Boolean! = true) {  insert_queries_one(object: $object, on_conflict: $on_conflict) {    ...Query    favorite_queries(where: {user_id: {_eq: $session_id}}, limit: 1) {      created_at      __typename    }    __typename  }}fragment Query on queries {  ...BaseQuery  ...QueryVisualizations  ...QueryForked  ...QueryUsers  ...QueryTeams  ...QueryFavorites  __typename}fragment BaseQuery on queries {  id  dataset_id  name  description  query  is_private  is_temp  is_archived  created_at  updated_at  schedule  tags  parameters  __typename}fragment QueryVisualizations on queries {  visualizations {    id    type    name    options    created_at    __typename  }  __typename}fragment QueryForked on queries {  forked_query {    id    name    user {      name      __typename    }    team {      handle      __typename    }    __typename  }  __typename}fragment QueryUsers on queries {  user {    ...User    __typename  }  team {    id    name    handle    profile_image_url    __typename  }  __typename}fragment User on users {  id  name  profile_image_url  __typename}fragment QueryTeams on queries {  team {    ...Team    __typename  }  __typename}fragment Team on teams {  id  name  handle  profile_image_url  __typename}fragment QueryFavorites on queries {  query_favorite_count_all @include(if: $favs_all_time) {    favorite_count    __typename  }  query_favorite_count_last_24h @include(if: $favs_last_24h) {    favorite_count    __typename  }  query_favorite_count_last_7d @include(if: $favs_last_7d) {    favorite_count    __typename  }  query_favorite_count_last_30d @include(if: $favs_last_30d) {    favorite_count    __typename  }  __typename}"#,
+            variables: UpsertQueryVariables::new(
+                user_id, session_id, dataset_id, name, query, parameters,
+            ),
         }
     }
 }
 
+impl GraphQLOperation for UpsertQuery {
+    const NAME: &'static str = "UpsertQuery";
+    const QUERY: &'static str = r#"mutation UpsertQuery($session_id: Int!, $object: queries_insert_input!, $on_conflict: queries_on_conflict!, $favs_last_24h: Boolean! = false, $favs_last_7d: Boolean! = false, $favs_last_30d: Boolean! = false, $favs_all_time: Boolean! = true) {  insert_queries_one(object: $object, on_conflict: $on_conflict) {    ...Query    favorite_queries(where: {user_id: {_eq: $session_id}}, limit: 1) {      created_at      __typename    }    __typename  }}fragment Query on queries {  ...BaseQuery  ...QueryVisualizations  ...QueryForked  ...QueryUsers  ...QueryTeams  ...QueryFavorites  __typename}fragment BaseQuery on queries {  id  dataset_id  name  description  query  is_private  is_temp  is_archived  created_at  updated_at  schedule  tags  parameters  __typename}fragment QueryVisualizations on queries {  visualizations {    id    type    name    options    created_at    __typename  }  __typename}fragment QueryForked on queries {  forked_query {    id    name    user {      name      __typename    }    team {      handle      __typename    }    __typename  }  __typename}fragment QueryUsers on queries {  user {    ...User    __typename  }  team {    id    name    handle    profile_image_url    __typename  }  __typename}fragment User on users {  id  name  profile_image_url  __typename}fragment QueryTeams on queries {  team {    ...Team    __typename  }  __typename}fragment Team on teams {  id  name  handle  profile_image_url  __typename}fragment QueryFavorites on queries {  query_favorite_count_all @include(if: $favs_all_time) {    favorite_count    __typename  }  query_favorite_count_last_24h @include(if: $favs_last_24h) {    favorite_count    __typename  }  query_favorite_count_last_7d @include(if: $favs_last_7d) {    favorite_count    __typename  }  query_favorite_count_last_30d @include(if: $favs_last_30d) {    favorite_count    __typename  }  __typename}"#;
+
+    type Variables = UpsertQueryVariables;
+    type Response = UpsertQueryResponse;
+
+    fn variables(&self) -> &Self::Variables {
+        &self.variables
+    }
+}
+
 #[derive(Deserialize)]
 struct UpsertQueryResponseDataInsertQueriesOne {
     #[serde(rename = "id")]
     query_id: i32,
+    #[serde(default)]
+    parameters: Option<Vec<Parameter>>,
 }
 
 #[derive(Deserialize)]
@@ -162,6 +206,15 @@ impl UpsertQueryResponse {
     pub(crate) fn query_id(&self) -> i32 {
         self.data.insert_queries_one.query_id
     }
+
+    /// The parameters Dune saved with the query.
+    pub(crate) fn parameters(&self) -> &[Parameter] {
+        self.data
+            .insert_queries_one
+            .parameters
+            .as_deref()
+            .unwrap_or_default()
+    }
 }
 
 #[test]
@@ -211,4 +264,5 @@ fn deserialize_upsert_query_response_should_ok() {
 
     let res = serde_json::from_str::<UpsertQueryResponse>(json).unwrap();
     assert_eq!(res.query_id(), 917003);
+    assert!(res.parameters().is_empty());
 }
@@ -0,0 +1,57 @@
+//! Crawls Dune address labels into PostgreSQL, and runs Dune SQL for other services through
+//! [`DuneClient`].
+
+use clap::Parser;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+mod backend;
+mod catalog;
+mod cli;
+mod client;
+mod command;
+mod configuration;
+mod domain;
+mod query_task;
+mod scheduler;
+mod session;
+mod sql;
+#[cfg(test)]
+mod stub;
+
+pub use client::DuneClient;
+pub use configuration::Engine;
+pub use domain::{DuneError, GraphQLError, QueryError};
+
+use cli::*;
+use configuration::*;
+
+fn get_connection_pool(settings: &DatabaseSettings) -> PgPool {
+    PgPoolOptions::new().connect_lazy_with(settings.with_db())
+}
+
+/// Run the `dune-crawler` command line.
+pub async fn run() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    command::init_logger();
+    let settings = Settings::new(cli.config(), cli.environment())?;
+
+    match cli.command() {
+        Command::Crawl {
+            dry_run: true,
+            format,
+        } => command::plan(&settings, *format).await,
+        Command::Crawl { dry_run: false, .. } => command::crawl(settings).await,
+        Command::Catalog { explain } => command::catalog(&settings, *explain).await,
+        Command::Export { format, output } => {
+            command::export(&settings, *format, output.as_deref()).await
+        }
+        Command::Status => command::status(&settings).await,
+        Command::Migrate => command::migrate(&settings).await,
+        Command::Check => command::check(&settings).await,
+        Command::Cleanup {
+            delete,
+            older_than,
+            dry_run,
+        } => command::cleanup(&settings, *delete, *older_than, *dry_run).await,
+    }
+}
@@ -1,223 +1,462 @@
-use std::{
-    fmt::{Debug, Formatter},
-    task::{Context, Poll},
+use std::fmt::{Debug, Display, Formatter};
+
+use futures_util::stream::{self, BoxStream, StreamExt};
+use tokio::sync::watch;
+
+use crate::{
+    catalog::LabelData,
+    client::DuneClient,
+    configuration::Engine,
+    domain::*,
+    sql::{quote_literal, quote_spark_literal, quote_standard_literal, Select},
 };
 
-use futures_util::{future::BoxFuture, ready, stream::Stream, TryFutureExt};
-use reqwest::Client;
-use serde::Deserialize;
-
-use crate::domain::*;
-
-enum QueryTaskState {
-    RefreshSession(Option<BoxFuture<'static, Result<SessionResponse, anyhow::Error>>>),
-    UpsertQuery(BoxFuture<'static, Result<UpsertQueryResponse, anyhow::Error>>),
-    ExecuteQuery(BoxFuture<'static, Result<String, anyhow::Error>>),
-    GetQueuePosition(
-        (
-            String,
-            Option<BoxFuture<'static, Result<String, anyhow::Error>>>,
-        ),
-    ),
-    FindResult(BoxFuture<'static, Result<String, anyhow::Error>>),
+/// The most rows a single page query asks Dune for.
+pub(crate) const PAGE_SIZE: usize = 100000;
+
+/// The `LIMIT` of the next page when `amount` labels are still to be fetched.
+pub(crate) fn page_limit(amount: usize) -> usize {
+    std::cmp::min(PAGE_SIZE, amount)
 }
 
-#[derive(Deserialize)]
-struct SessionResponse {
-    token: String,
+/// The saved query every page of every label category runs with its own [`label_parameters`],
+/// written for `engine`.
+///
+/// The `label_type` of a catalog category is what the legacy labels table calls `type` and the
+/// Spellbook one `category`, so that is the column it filters on. Spellbook has a `label_type` of
+/// its own (`identifier`, `usage` or `persona`), which is selected as is. Only the Spellbook table
+/// has the [`LabelDetails`] of a label.
+pub(crate) fn label_query(engine: Engine) -> String {
+    let select = match engine {
+        // `labels.labels` only has Ethereum labels, the chain is selected as given.
+        Engine::Postgres => Select::new(
+            &[
+                "{{blockchain}} AS blockchain",
+                "address",
+                "name AS label_name",
+                "type AS label_type",
+            ],
+            "labels.labels",
+        )
+        .condition("type = {{label_type}}")
+        .condition("name = {{label_name}}")
+        .condition("octet_length(address) > 0")
+        .condition("address > {{cursor}}"),
+        Engine::Spark => Select::new(
+            &[
+                "blockchain",
+                "address",
+                "name AS label_name",
+                "label_type",
+                "category",
+                "contributor",
+                "source",
+                "created_at",
+                "updated_at",
+                "model_name",
+            ],
+            "labels.all",
+        )
+        .condition("blockchain = {{blockchain}}")
+        .condition("category = {{label_type}}")
+        .condition("name = {{label_name}}")
+        .condition("length(address) > 0")
+        .condition("address > {{cursor}}"),
+        Engine::DuneSql => Select::new(
+            &[
+                "blockchain",
+                "address",
+                "name AS label_name",
+                "label_type",
+                "category",
+                "contributor",
+                "source",
+                "created_at",
+                "updated_at",
+                "model_name",
+            ],
+            "labels.all",
+        )
+        .condition("blockchain = {{blockchain}}")
+        .condition("category = {{label_type}}")
+        .condition("name = {{label_name}}")
+        .condition("length(address) > 0")
+        .condition("address > from_hex({{cursor}})"),
+    };
+
+    select
+        .order_by("address ASC")
+        .limit("{{limit}}")
+        .to_string()
 }
 
+/// The parameters of the page of a label category of `blockchain` that starts after
+/// `base_address` (`0x...`).
+///
+/// Dune pastes text parameters into the SQL unquoted, so they are sent as literals quoted for
+/// `engine`. The cursor is written the way `engine` compares addresses: a `bytea` escape on the
+/// legacy engine, the `0x` string itself on Spark, and the bare hex `from_hex` reads on DuneSQL.
+pub(crate) fn label_parameters(
+    engine: Engine,
+    blockchain: &str,
+    label_type: &str,
+    label_name: &str,
+    base_address: Option<&str>,
+    limit: usize,
+) -> Vec<Parameter> {
+    let quote = match engine {
+        Engine::Postgres => quote_literal,
+        Engine::Spark => quote_spark_literal,
+        Engine::DuneSql => quote_standard_literal,
+    };
+    let cursor = base_address
+        .map(|address| {
+            let hex = address.strip_prefix("0x").unwrap_or(address);
+            match engine {
+                Engine::Postgres => format!("\\x{}", hex),
+                Engine::Spark => format!("0x{}", hex),
+                Engine::DuneSql => hex.to_owned(),
+            }
+        })
+        .unwrap_or_default();
+
+    vec![
+        Parameter::text("blockchain", quote(blockchain)),
+        Parameter::text("label_type", quote(label_type)),
+        Parameter::text("label_name", quote(label_name)),
+        Parameter::text("cursor", quote(&cursor)),
+        Parameter::number("limit", limit),
+    ]
+}
+
+/// The step a [`TaskPhase`] is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TaskStep {
+    /// Not started, or between two pages
+    Pending,
+    /// Starting the job of the next page
+    Executing,
+    /// Waiting for the job to leave the queue and finish
+    Waiting,
+    /// Fetching the rows of the finished job
+    Fetching,
+    /// Every page is fetched
+    Done,
+    /// Gave up after an error
+    Failed,
+}
+
+/// The live state of a label category crawl.
+#[derive(Debug, Clone)]
+pub(crate) struct TaskPhase {
+    step: TaskStep,
+    query_id: i32,
+    job_id: Option<String>,
+    pages_done: usize,
+    rows_remaining: usize,
+}
+
+impl TaskPhase {
+    pub(crate) fn step(&self) -> TaskStep {
+        self.step
+    }
+
+    /// The saved query every page runs.
+    pub(crate) fn query_id(&self) -> i32 {
+        self.query_id
+    }
+
+    /// The job of the current page, once it is started.
+    pub(crate) fn job_id(&self) -> Option<&str> {
+        self.job_id.as_deref()
+    }
+
+    pub(crate) fn pages_done(&self) -> usize {
+        self.pages_done
+    }
+
+    /// How many more rows the category is expected to have.
+    pub(crate) fn rows_remaining(&self) -> usize {
+        self.rows_remaining
+    }
+}
+
+impl Display for TaskPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} after {} pages, {} rows remaining, query {}",
+            self.step(),
+            self.pages_done(),
+            self.rows_remaining(),
+            self.query_id()
+        )?;
+
+        match self.job_id() {
+            Some(job_id) => write!(f, ", job {}", job_id),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Crawls a label category page by page: execute the label query, wait for its job, fetch it.
 pub(crate) struct QueryTask {
-    userid: i32,
-    client: Client,
+    client: DuneClient,
+    engine: Engine,
+    blockchain: String,
     label_type: String,
     label_name: String,
     base_address: Option<String>,
-    bearer_token: String,
-    amount: usize,
-    state: QueryTaskState,
+    phase: watch::Sender<TaskPhase>,
 }
 
 impl Debug for QueryTask {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "type: {}, name: {}, amount: {}",
-            self.label_type, self.label_name, self.amount
+            "chain: {}, type: {}, name: {}, amount: {}",
+            self.blockchain,
+            self.label_type,
+            self.label_name,
+            self.phase.borrow().rows_remaining
         )
     }
 }
 
 impl QueryTask {
-    pub(crate) fn new(
-        userid: i32,
-        client: Client,
-        label_type: String,
-        label_name: String,
-        amount: usize,
-    ) -> Self {
+    pub(crate) fn new(client: DuneClient, engine: Engine, query_id: i32, data: LabelData) -> Self {
+        let (blockchain, label_type, label_name, amount) = data.into_parts();
+        let (phase, _) = watch::channel(TaskPhase {
+            step: TaskStep::Pending,
+            query_id,
+            job_id: None,
+            pages_done: 0,
+            rows_remaining: amount,
+        });
+
         Self {
             client,
-            userid,
+            engine,
+            blockchain,
             label_type,
             label_name,
             base_address: None,
-            bearer_token: String::new(),
-            amount,
-            state: QueryTaskState::RefreshSession(None),
+            phase,
         }
     }
-}
 
-impl Stream for QueryTask {
-    type Item = Result<Vec<AddressLabel>, anyhow::Error>;
-
-    fn poll_next(
-        mut self: std::pin::Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        match self.state {
-            QueryTaskState::RefreshSession(ref mut fut) => match fut {
-                Some(ref mut fut) => ready!(fut.as_mut().poll(cx))
-                    .map(|response| {
-                        self.bearer_token = response.token;
-                        let limit = std::cmp::min(100000, self.amount);
-
-                        let mut where_clause = format!(
-                            "type = '{}' AND name = '{}' AND octet_length(address) > 0",
-                            self.label_type, self.label_name
-                        );
-
-                        if let Some(ref base_address) = self.base_address {
-                            where_clause.push_str(&format!("AND address > '{}'", base_address));
-                        }
-
-                        let query = format!("SELECT address, name AS label_name, type AS label_type FROM labels.labels WHERE {} ORDER BY address ASC LIMIT {}", where_clause, limit);
-
-                        //TODO: user_id
-                        let fut = self
-                            .client
-                            .post("https://core-hsr.duneanalytics.com/v1/graphql")
-                            .bearer_auth(&self.bearer_token)
-                            .json(&UpsertQuery::new(self.userid, self.userid, query))
-                            .send()
-                            .and_then(|response| response.json::<UpsertQueryResponse>())
-                            .map_err(Into::into);
-
-                        self.state = QueryTaskState::UpsertQuery(Box::pin(fut));
-                        cx.waker().wake_by_ref();
-                        Poll::Pending
-                    })
-                    .unwrap_or_else(|err| Poll::Ready(Some(Err(err)))),
-                None => {
-                    if self.amount == 0 {
-                        return Poll::Ready(None);
-                    }
-
-                    let fut = self
-                        .client
-                        .post("https://dune.com/api/auth/session")
-                        .send()
-                        .and_then(|response| response.json::<SessionResponse>())
-                        .map_err(Into::into);
-
-                    self.state = QueryTaskState::RefreshSession(Some(Box::pin(fut)));
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                }
-            },
-            QueryTaskState::UpsertQuery(ref mut fut) => ready!(fut.as_mut().poll(cx))
-                .map(|response| {
-                    let fut = self
-                        .client
-                        .post("https://core-hsr.duneanalytics.com/v1/graphql")
-                        .bearer_auth(&self.bearer_token)
-                        .json(&crate::domain::ExecuteQuery::new(response.query_id()))
-                        .send()
-                        .and_then(|response| response.text())
-                        .map_err(Into::into);
-
-                    self.state = QueryTaskState::ExecuteQuery(Box::pin(fut));
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                })
-                .unwrap_or_else(|err| Poll::Ready(Some(Err(err)))),
-            QueryTaskState::ExecuteQuery(ref mut fut) => {
-                ready!(fut.as_mut().poll(cx).map_ok(|res| {
-                    match serde_json::from_str::<ExecuteQueryResponse>(&res) {
-                        Ok(json) => json,
-                        Err(err) => panic!("{}, {}", res, err),
-                    }
-                }))
-                .map(|response| {
-                    self.state = QueryTaskState::GetQueuePosition((response.job_id(), None));
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                })
-                .unwrap_or_else(|err| Poll::Ready(Some(Err(err))))
+    /// The label category, as `chain / type / name`.
+    pub(crate) fn category(&self) -> String {
+        format!(
+            "{} / {} / {}",
+            self.blockchain, self.label_type, self.label_name
+        )
+    }
+
+    /// Follow the phase of the task as it runs.
+    pub(crate) fn phase(&self) -> watch::Receiver<TaskPhase> {
+        self.phase.subscribe()
+    }
+
+    /// The pages of the category, ending after the last one or the first error.
+    pub(crate) fn into_stream(self) -> BoxStream<'static, Result<Vec<AddressLabel>, DuneError>> {
+        stream::unfold(self, |mut task| async move {
+            let page = task.next_page().await?;
+            Some((page, task))
+        })
+        .boxed()
+    }
+
+    async fn next_page(&mut self) -> Option<Result<Vec<AddressLabel>, DuneError>> {
+        let phase = self.phase.borrow().clone();
+        if matches!(phase.step, TaskStep::Done | TaskStep::Failed) {
+            return None;
+        }
+
+        if phase.rows_remaining == 0 {
+            self.update(|phase| phase.step = TaskStep::Done);
+            return None;
+        }
+
+        match self.fetch_page(phase.query_id, phase.rows_remaining).await {
+            Ok(page) if page.is_empty() => {
+                self.update(|phase| phase.step = TaskStep::Done);
+                None
             }
-            QueryTaskState::GetQueuePosition((ref job_id, ref mut fut)) => {
-                let job_id = job_id.to_string();
-
-                if let Some(ref mut fut) = fut {
-                    match ready!(
-                        fut.as_mut().poll(cx).map_ok(|res| {
-                        match serde_json::from_str::<GetQueuePositionResponse>(&res) {
-                            Ok(json) => json,
-                            Err(err) => panic!("{}, {}", res, err),
-                        }
-                    })
-                    ) {
-                        Ok(response) => {
-                            if !response.is_executing() {
-                                let fut = self
-                                    .client
-                                    .post("https://core-hsr.duneanalytics.com/v1/graphql")
-                                    .bearer_auth(&self.bearer_token)
-                                    .json(&FindResultDataByJobId::new(job_id))
-                                    .send()
-                                    .and_then(|response| response.text())
-                                    .map_err(Into::into);
-
-                                self.state = QueryTaskState::FindResult(Box::pin(fut));
-                                cx.waker().wake_by_ref();
-                                return Poll::Pending;
-                            }
-                        }
-                        Err(err) => return Poll::Ready(Some(Err(err))),
-                    }
-                }
-
-                let fut = self
-                    .client
-                    .post("https://core-hsr.duneanalytics.com/v1/graphql")
-                    .bearer_auth(&self.bearer_token)
-                    .json(&crate::domain::GetQueuePosition::new(job_id.clone()))
-                    .send()
-                    .and_then(|response| response.text())
-                    .map_err(Into::into);
-
-                self.state = QueryTaskState::GetQueuePosition((job_id, Some(Box::pin(fut))));
-                cx.waker().wake_by_ref();
-                Poll::Pending
+            Ok(page) => {
+                self.base_address = page.last().map(|last| last.address());
+                self.update(|phase| {
+                    phase.step = TaskStep::Pending;
+                    phase.pages_done += 1;
+                    phase.rows_remaining = phase.rows_remaining.saturating_sub(page.len());
+                });
+                Some(Ok(page))
+            }
+            Err(err) => {
+                self.update(|phase| phase.step = TaskStep::Failed);
+                Some(Err(err))
             }
-            QueryTaskState::FindResult(ref mut fut) => ready!(
-                fut.as_mut().poll(cx).map_ok(|res| {
-                    match serde_json::from_str::<FindResultDataResponse<AddressLabel>>(&res) {
-                        Ok(json) => json.data(),
-                        Err(err) => panic!("{},{}", res, err),
-                    }
-                })
-            ).map(|data| {
-                if let Some(last) = data.last() {
-                    self.amount -= data.len();
-                    self.base_address = Some(last.address().to_owned());
-                    self.state = QueryTaskState::RefreshSession(None);
-                    Poll::Ready(Some(Ok(data)))
-                } else {
-                    Poll::Ready(None)
-                }
-            }).unwrap_or_else(|err| Poll::Ready(Some(Err(err)))),
         }
     }
+
+    async fn fetch_page(
+        &self,
+        query_id: i32,
+        rows_remaining: usize,
+    ) -> Result<Vec<AddressLabel>, DuneError> {
+        self.update(|phase| {
+            phase.step = TaskStep::Executing;
+            phase.job_id = None;
+        });
+        let parameters = label_parameters(
+            self.engine,
+            &self.blockchain,
+            &self.label_type,
+            &self.label_name,
+            self.base_address.as_deref(),
+            page_limit(rows_remaining),
+        );
+        let job_id = self.client.execute(query_id, parameters).await?;
+
+        self.update(|phase| {
+            phase.step = TaskStep::Waiting;
+            phase.job_id = Some(job_id.clone());
+        });
+        self.client.wait(&job_id).await?;
+
+        self.update(|phase| phase.step = TaskStep::Fetching);
+        self.client.fetch(job_id).await
+    }
+
+    fn update(&self, modify: impl FnOnce(&mut TaskPhase)) {
+        self.phase.send_modify(modify);
+    }
+}
+
+#[test]
+fn label_parameters_should_quote_label_values() {
+    assert_eq!(
+        label_parameters(
+            Engine::Postgres,
+            "ethereum",
+            "dao",
+            "Ol' Bob",
+            Some("0x00ff"),
+            10
+        )
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>(),
+        vec![
+            "blockchain='ethereum'",
+            "label_type='dao'",
+            "label_name='Ol'' Bob'",
+            r"cursor=E'\\x00ff'",
+            "limit=10"
+        ]
+    );
+    assert_eq!(
+        label_parameters(Engine::Postgres, "ethereum", "dao", "x", None, 10)[3].to_string(),
+        "cursor=''"
+    );
+}
+
+#[test]
+fn label_parameters_should_follow_the_engine() {
+    let cursor = |engine| {
+        label_parameters(engine, "polygon", "dao", "Ol' Bob", Some("0x00ff"), 10)
+            .into_iter()
+            .skip(2)
+            .take(2)
+            .map(|parameter| parameter.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        cursor(Engine::Spark),
+        vec![r"label_name='Ol\' Bob'", "cursor='0x00ff'"]
+    );
+    assert_eq!(
+        cursor(Engine::DuneSql),
+        vec!["label_name='Ol'' Bob'", "cursor='00ff'"]
+    );
+}
+
+#[test]
+fn label_query_should_follow_the_engine() {
+    assert!(
+        label_query(Engine::Postgres).contains("FROM labels.labels WHERE type = {{label_type}}")
+    );
+    assert!(label_query(Engine::Spark).contains("blockchain = {{blockchain}}"));
+    assert!(label_query(Engine::DuneSql)
+        .contains("name AS label_name, label_type, category, contributor"));
+    assert!(label_query(Engine::DuneSql).contains("category = {{label_type}}"));
+    assert!(label_query(Engine::Spark).contains("address > {{cursor}}"));
+    assert!(label_query(Engine::DuneSql).contains("address > from_hex({{cursor}})"));
+}
+
+#[test]
+fn task_phase_should_display_its_job() {
+    let mut phase = TaskPhase {
+        step: TaskStep::Waiting,
+        query_id: 42,
+        job_id: Some(String::from("job-1")),
+        pages_done: 2,
+        rows_remaining: 5,
+    };
+    assert_eq!(
+        phase.to_string(),
+        "Waiting after 2 pages, 5 rows remaining, query 42, job job-1"
+    );
+
+    phase.job_id = None;
+    assert!(!phase.to_string().contains("job"));
+}
+
+#[tokio::test]
+async fn query_task_should_publish_its_phase() {
+    use crate::stub::{response, serve};
+
+    // Every page has a single label.
+    let url = serve(|request| {
+        let body = match request.lines().next().unwrap_or_default() {
+            line if line.starts_with("POST /query/42/execute") => {
+                r#"{"execution_id":"exec-1","state":"QUERY_STATE_PENDING"}"#
+            }
+            line if line.contains("/status") => r#"{"state":"QUERY_STATE_COMPLETED"}"#,
+            _ => {
+                r#"{"state":"QUERY_STATE_COMPLETED","result":{"rows":[{"blockchain":"polygon","address":"0x01","label_name":"x","label_type":"identifier"}]},"next_uri":null}"#
+            }
+        };
+        response("200 OK", &[], body)
+    })
+    .await;
+
+    let client = DuneClient::with_api_url("key", &url).unwrap();
+    let data = serde_json::from_value::<LabelData>(serde_json::json!({
+        "blockchain": "polygon",
+        "label_type": "dao",
+        "label_name": "x",
+        "amount": 2,
+    }))
+    .unwrap();
+    let task = QueryTask::new(client, Engine::DuneSql, 42, data);
+    let phase = task.phase();
+    assert_eq!(phase.borrow().step(), TaskStep::Pending);
+
+    let mut pages = task.into_stream();
+    assert_eq!(pages.next().await.unwrap().unwrap().len(), 1);
+    {
+        let phase = phase.borrow();
+        assert_eq!(phase.step(), TaskStep::Pending);
+        assert_eq!(phase.query_id(), 42);
+        assert_eq!(phase.job_id(), Some("exec-1"));
+        assert_eq!(phase.pages_done(), 1);
+        assert_eq!(phase.rows_remaining(), 1);
+    }
+
+    assert_eq!(pages.next().await.unwrap().unwrap().len(), 1);
+    assert!(pages.next().await.is_none());
+    assert_eq!(phase.borrow().step(), TaskStep::Done);
+    assert_eq!(phase.borrow().pages_done(), 2);
 }
@@ -0,0 +1,100 @@
+use futures_util::{
+    future,
+    stream::{self, BoxStream, SelectAll, Stream, StreamExt},
+};
+
+/// Drive `tasks` to completion, at most `concurrency` of them at once, yielding the items of every
+/// task as they come.
+///
+/// The next task starts as soon as a running one ends, and the stream ends once every task did.
+pub(crate) fn schedule<I, S>(tasks: I, concurrency: usize) -> impl Stream<Item = S::Item>
+where
+    I: IntoIterator<Item = S>,
+    S: Stream + Send + 'static,
+    S::Item: Send + 'static,
+{
+    let concurrency = concurrency.max(1);
+    let state = (
+        tasks.into_iter(),
+        SelectAll::<BoxStream<'static, Option<S::Item>>>::new(),
+        0,
+    );
+
+    stream::unfold(
+        state,
+        move |(mut pending, mut active, mut running)| async move {
+            loop {
+                while running < concurrency {
+                    match pending.next() {
+                        // Every task ends with a `None`, telling when the next one may start.
+                        Some(task) => {
+                            active.push(
+                                task.map(Some)
+                                    .chain(stream::once(future::ready(None)))
+                                    .boxed(),
+                            );
+                            running += 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                match active.next().await {
+                    Some(Some(item)) => return Some((item, (pending, active, running))),
+                    Some(None) => running -= 1,
+                    None => return None,
+                }
+            }
+        },
+    )
+}
+
+#[tokio::test]
+async fn schedule_should_drain_every_task_within_the_limit() {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let most_running = Arc::new(AtomicUsize::new(0));
+
+    // Five tasks of 1 to 5 pages each, their pages taking different times to come.
+    let tasks = (1..=5usize).map(|task| {
+        let running = running.clone();
+        let most_running = most_running.clone();
+
+        stream::unfold(0, move |page| {
+            let running = running.clone();
+            let most_running = most_running.clone();
+
+            async move {
+                if page == 0 {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now, Ordering::SeqCst);
+                }
+                if page == task {
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    return None;
+                }
+
+                tokio::time::sleep(Duration::from_millis((6 - task as u64) * 3)).await;
+                Some(((task, page), page + 1))
+            }
+        })
+    });
+
+    let mut pages = schedule(tasks, 2).collect::<Vec<_>>().await;
+    pages.sort_unstable();
+
+    assert_eq!(
+        pages,
+        (1..=5)
+            .flat_map(|task| (0..task).map(move |page| (task, page)))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(most_running.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,353 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use log::warn;
+use reqwest::{Client, StatusCode, Url};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::{configuration::*, domain::*};
+
+/// Tokens this close to their expiry are refreshed before being handed out.
+const TOKEN_EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// A bearer token and, when it could be decoded from the JWT, its `exp` claim.
+#[derive(Clone, Deserialize, Serialize)]
+struct BearerToken {
+    token: String,
+    expires_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    exp: u64,
+}
+
+impl BearerToken {
+    fn new(token: String) -> Self {
+        let expires_at = token
+            .split('.')
+            .nth(1)
+            .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+            .and_then(|payload| serde_json::from_slice::<JwtClaims>(&payload).ok())
+            .map(|claims| claims.exp);
+
+        Self { token, expires_at }
+    }
+
+    /// Tokens without a readable expiry are never reused.
+    fn is_fresh(&self, now: u64) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now + TOKEN_EXPIRY_MARGIN_SECS < expires_at)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Keeps the Dune browser session alive.
+///
+/// The session cookies live in a jar, so the `auth-refresh` and `csrf` cookies Dune rotates on
+/// every session refresh are sent back on the next one. With `application.cookie_jar` set, the jar
+/// is saved after every refresh and reloaded on start, so the rotated cookies survive restarts too.
+///
+/// The bearer token is shared by every task and only renewed when it is about to expire, or was
+/// rejected. With `application.token_cache` set it is kept on disk between runs.
+pub(crate) struct SessionManager {
+    client: Client,
+    jar: Arc<CookieStoreMutex>,
+    jar_path: Option<PathBuf>,
+    session_url: Url,
+    /// The configured cookie, to sign in with once the cookies loaded from the jar are rejected
+    seed: std::sync::Mutex<Option<Secret<String>>>,
+    token: Mutex<Option<BearerToken>>,
+    token_path: Option<PathBuf>,
+}
+
+impl SessionManager {
+    pub(crate) fn new(
+        application: &ApplicationSettings,
+        dune: &DuneSettings,
+    ) -> Result<Self, anyhow::Error> {
+        let session_url = Url::parse(dune.session_url())?;
+        let jar_path = application.cookie_jar().map(ToOwned::to_owned);
+
+        let (store, seed) = match jar_path.as_ref().filter(|path| path.exists()) {
+            Some(path) => (
+                CookieStore::load_json(BufReader::new(File::open(path)?)).map_err(|err| {
+                    anyhow!("failed to load cookie jar {}, {}", path.display(), err)
+                })?,
+                application.cookie().ok(),
+            ),
+            None => (seed_store(&application.cookie()?, &session_url)?, None),
+        };
+
+        let jar = Arc::new(CookieStoreMutex::new(store));
+        let client = Client::builder().cookie_provider(jar.clone()).build()?;
+
+        let token_path = application.token_cache().map(ToOwned::to_owned);
+        let token = token_path
+            .as_deref()
+            .and_then(load_token)
+            .filter(|token| token.is_fresh(now()));
+
+        Ok(Self {
+            client,
+            jar,
+            jar_path,
+            session_url,
+            seed: std::sync::Mutex::new(seed),
+            token: Mutex::new(token),
+            token_path,
+        })
+    }
+
+    /// A bearer token valid for a while yet, refreshing the session only when needed.
+    pub(crate) async fn token(&self) -> Result<String, DuneError> {
+        let mut cached = self.token.lock().await;
+
+        if let Some(token) = cached.as_ref().filter(|token| token.is_fresh(now())) {
+            return Ok(token.token.clone());
+        }
+
+        let token = BearerToken::new(self.refresh().await?.token());
+        if let Some(ref path) = self.token_path {
+            let content = serde_json::to_vec(&token).unwrap_or_default();
+            if let Err(err) = write_private(path, &content).await {
+                warn!("failed to save token cache {}, {}", path.display(), err);
+            }
+        }
+
+        Ok(cached.insert(token).token.clone())
+    }
+
+    /// Forget `token` after Dune rejected it, unless it was already replaced.
+    pub(crate) async fn invalidate(&self, token: &str) {
+        let mut cached = self.token.lock().await;
+
+        if cached.as_ref().map(|cached| cached.token.as_str()) == Some(token) {
+            *cached = None;
+        }
+    }
+
+    /// Execute `operation` with the bearer token, renewing the token once if Dune rejects it.
+    pub(crate) async fn execute<O>(
+        &self,
+        url: &str,
+        operation: &O,
+    ) -> Result<O::Response, DuneError>
+    where
+        O: GraphQLOperation,
+    {
+        let mut renewed = false;
+
+        loop {
+            let token = self.token().await?;
+
+            match operation.execute(&self.client, url, Some(&token)).await {
+                Err(DuneError::SessionExpired) if !renewed => {
+                    self.invalidate(&token).await;
+                    renewed = true;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Exchange the session cookies for a new bearer token.
+    ///
+    /// When Dune rejects the cookies of the jar, the configured cookie is tried once in their place,
+    /// so a fresh cookie takes over from a stale jar without deleting it by hand.
+    pub(crate) async fn refresh(&self) -> Result<SessionResponse, DuneError> {
+        match self.request_token().await {
+            Err(DuneError::SessionExpired) if self.reseed() => self.request_token().await,
+            result => result,
+        }
+    }
+
+    /// Replace the cookies of the jar with the configured cookie, unless that was done already.
+    fn reseed(&self) -> bool {
+        let seed = match self.seed.lock().ok().and_then(|mut seed| seed.take()) {
+            Some(seed) => seed,
+            None => return false,
+        };
+
+        match (seed_store(&seed, &self.session_url), self.jar.lock()) {
+            (Ok(seeded), Ok(mut store)) => {
+                warn!("the saved session cookies were rejected, using the configured cookie");
+                *store = seeded;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    async fn request_token(&self) -> Result<SessionResponse, DuneError> {
+        let response = self.client.post(self.session_url.clone()).send().await?;
+
+        // Dune answers the session endpoint with 401/403 once `auth-refresh` itself has expired,
+        // only signing in again in a browser helps then.
+        let (status, body) = match read_response(response).await? {
+            (StatusCode::FORBIDDEN, body) => (StatusCode::UNAUTHORIZED, body),
+            response => response,
+        };
+        let session = decode_response::<SessionResponse>(status, body)?;
+
+        if let Err(err) = self.persist().await {
+            warn!("failed to save cookie jar, {}", err);
+        }
+
+        Ok(session)
+    }
+
+    async fn persist(&self) -> Result<(), anyhow::Error> {
+        let path = match self.jar_path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let mut content = Vec::new();
+        self.jar
+            .lock()
+            .map_err(|_| anyhow!("the cookie jar is poisoned"))?
+            .save_incl_expired_and_nonpersistent_json(&mut content)
+            .map_err(|err| anyhow!("failed to save cookie jar {}, {}", path.display(), err))?;
+
+        write_private(path, &content).await?;
+
+        Ok(())
+    }
+}
+
+/// Replace the file at `path` with `content`, readable by its owner only as it holds secrets.
+async fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    // `.cookies.json.tmp` next to `cookies.json`, a name `.gitignore` covers.
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    // A temp file left behind keeps its permissions when reopened.
+    let _ = tokio::fs::remove_file(&temp).await;
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let written = async {
+        let mut file = options.open(&temp).await?;
+        file.write_all(content).await?;
+        file.flush().await?;
+        drop(file);
+        tokio::fs::rename(&temp, path).await
+    }
+    .await;
+
+    // Nothing holding the secrets is left behind.
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+
+    written
+}
+
+/// A cookie store holding the `name=value` pairs of `cookie`.
+fn seed_store(cookie: &Secret<String>, session_url: &Url) -> Result<CookieStore, anyhow::Error> {
+    let mut store = CookieStore::default();
+
+    for pair in cookie.expose_secret().split(';') {
+        let pair = pair.trim();
+        if !pair.is_empty() {
+            store.parse(&format!("{}; Path=/", pair), session_url)?;
+        }
+    }
+
+    Ok(store)
+}
+
+fn load_token(path: &Path) -> Option<BearerToken> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+}
+
+#[test]
+fn bearer_token_should_expire_with_its_jwt() {
+    let payload = URL_SAFE_NO_PAD.encode(r#"{"sub":"121830","exp":1655400000}"#);
+    let token = BearerToken::new(format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", payload));
+
+    assert_eq!(token.expires_at, Some(1655400000));
+    assert!(token.is_fresh(1655400000 - TOKEN_EXPIRY_MARGIN_SECS - 1));
+    assert!(!token.is_fresh(1655400000 - TOKEN_EXPIRY_MARGIN_SECS));
+    assert!(!BearerToken::new(String::from("opaque")).is_fresh(0));
+}
+
+#[tokio::test]
+async fn refresh_should_fall_back_to_the_configured_cookie_when_the_jar_is_stale() {
+    use crate::stub::{response, serve};
+
+    // A session endpoint only taking the fresh `auth-refresh` cookie.
+    let url = serve(|request| match request.contains("auth-refresh=fresh") {
+        true => response("200 OK", &[], r#"{"token":"fresh"}"#),
+        false => response("401 Unauthorized", &[], "{}"),
+    })
+    .await;
+    let session_url = format!("{}/api/auth/session", url);
+
+    let dir = std::env::temp_dir().join(format!("dune-crawler-session-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let jar_path = dir.join("cookies.json");
+    let mut stale = seed_store(
+        &Secret::new(String::from("auth-refresh=stale")),
+        &Url::parse(&session_url).unwrap(),
+    )
+    .unwrap();
+    stale
+        .save_incl_expired_and_nonpersistent_json(&mut File::create(&jar_path).unwrap())
+        .unwrap();
+
+    let application = serde_json::from_value::<ApplicationSettings>(serde_json::json!({
+        "cookie": "auth-refresh=fresh",
+        "cookie_jar": jar_path,
+        "userid": 1,
+    }))
+    .unwrap();
+    let dune = DuneSettings::new(String::new(), session_url, Default::default());
+
+    let session = SessionManager::new(&application, &dune).unwrap();
+    assert_eq!(session.refresh().await.unwrap().token(), "fresh");
+    assert!(std::fs::read_to_string(&jar_path)
+        .unwrap()
+        .contains("fresh"));
+    assert!(!dir.join(".cookies.json.tmp").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&jar_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Once used, the configured cookie is not tried again.
+    stale = seed_store(
+        &Secret::new(String::from("auth-refresh=stale")),
+        &session.session_url,
+    )
+    .unwrap();
+    *session.jar.lock().unwrap() = stale;
+    assert!(matches!(
+        session.refresh().await,
+        Err(DuneError::SessionExpired)
+    ));
+}
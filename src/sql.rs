@@ -0,0 +1,137 @@
+use std::fmt::{Display, Formatter};
+
+/// Quote `value` as a PostgreSQL string literal, the way `quote_literal` does: quotes are doubled,
+/// and a value holding backslashes becomes an `E'...'` literal with them doubled too, so it reads
+/// the same whatever `standard_conforming_strings` is set to.
+pub(crate) fn quote_literal(value: &str) -> String {
+    let escaped = value.replace('\'', "''");
+
+    if value.contains('\\') {
+        format!("E'{}'", escaped.replace('\\', "\\\\"))
+    } else {
+        format!("'{}'", escaped)
+    }
+}
+
+/// Quote `value` as a Spark SQL string literal, where backslashes escape and quotes are
+/// backslash-escaped rather than doubled.
+pub(crate) fn quote_spark_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Quote `value` as a standard SQL string literal, the way Trino reads them: quotes are doubled and
+/// backslashes are nothing special.
+pub(crate) fn quote_standard_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// A `SELECT` with `AND`ed conditions.
+///
+/// Everything is written as given, only ever pass constants and `{{key}}` parameter placeholders,
+/// and send values as parameters quoted for the engine the query runs on, the way
+/// [`label_parameters`](crate::query_task::label_parameters) does.
+pub(crate) struct Select {
+    columns: Vec<&'static str>,
+    from: &'static str,
+    conditions: Vec<&'static str>,
+    order_by: Option<&'static str>,
+    limit: Option<&'static str>,
+}
+
+impl Select {
+    pub(crate) fn new(columns: &[&'static str], from: &'static str) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            from,
+            conditions: Vec::new(),
+            order_by: None,
+            limit: None,
+        }
+    }
+
+    /// A condition, e.g. `octet_length(address) > 0` or `name = {{label_name}}`.
+    pub(crate) fn condition(mut self, condition: &'static str) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub(crate) fn order_by(mut self, order_by: &'static str) -> Self {
+        self.order_by = Some(order_by);
+        self
+    }
+
+    pub(crate) fn limit(mut self, limit: &'static str) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl Display for Select {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SELECT {} FROM {}", self.columns.join(", "), self.from)?;
+
+        if !self.conditions.is_empty() {
+            write!(f, " WHERE {}", self.conditions.join(" AND "))?;
+        }
+
+        if let Some(order_by) = self.order_by {
+            write!(f, " ORDER BY {}", order_by)?;
+        }
+
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn quote_literal_should_escape_quotes() {
+    assert_eq!(quote_literal("Ol' Bob"), "'Ol'' Bob'");
+    assert_eq!(
+        quote_literal("'; DROP TABLE labels; --"),
+        "'''; DROP TABLE labels; --'"
+    );
+    assert_eq!(quote_literal(""), "''");
+}
+
+#[test]
+fn quote_literal_should_escape_backslashes() {
+    assert_eq!(quote_literal(r"C:\dao"), r"E'C:\\dao'");
+    assert_eq!(quote_literal(r"it\'s"), r"E'it\\''s'");
+}
+
+#[test]
+fn quote_literal_should_keep_unicode() {
+    assert_eq!(quote_literal("小狗 🐶"), "'小狗 🐶'");
+    assert_eq!(quote_literal("Ünïcødé’s"), "'Ünïcødé’s'");
+}
+
+#[test]
+fn quote_spark_literal_should_escape_with_backslashes() {
+    assert_eq!(quote_spark_literal("Ol' Bob"), r"'Ol\' Bob'");
+    assert_eq!(quote_spark_literal(r"C:\dao"), r"'C:\\dao'");
+    assert_eq!(quote_spark_literal(""), "''");
+}
+
+#[test]
+fn quote_standard_literal_should_only_double_quotes() {
+    assert_eq!(quote_standard_literal("Ol' Bob"), "'Ol'' Bob'");
+    assert_eq!(quote_standard_literal(r"C:\dao"), r"'C:\dao'");
+}
+
+#[test]
+fn select_should_join_conditions() {
+    let sql = Select::new(&["address", "name"], "labels.labels")
+        .condition("name = {{label_name}}")
+        .condition("octet_length(address) > 0")
+        .order_by("address ASC")
+        .limit("{{limit}}")
+        .to_string();
+
+    assert_eq!(
+        sql,
+        "SELECT address, name FROM labels.labels WHERE name = {{label_name}} AND octet_length(address) > 0 ORDER BY address ASC LIMIT {{limit}}"
+    );
+}
@@ -0,0 +1,102 @@
+//! A local HTTP server answering requests the way a test says Dune would.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Serve every request with `respond`, which is handed the whole request, head and body, and
+/// returns the whole response. The base URL of the server is returned.
+pub(crate) async fn serve<F>(respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle(stream, respond.clone()));
+        }
+    });
+
+    url
+}
+
+/// Serve `responses` one after the other, whatever the request, repeating the last one once they
+/// run out. The base URL of the server and the count of requests it got are returned.
+pub(crate) async fn serve_in_turn(responses: Vec<String>) -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let count = requests.clone();
+    let url = serve(move |_| {
+        let turn = count.fetch_add(1, Ordering::SeqCst);
+        responses[turn.min(responses.len() - 1)].clone()
+    })
+    .await;
+
+    (url, requests)
+}
+
+/// A response with `status` (e.g. `200 OK`), the extra `headers` lines and `body`.
+pub(crate) fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n", status, body.len());
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    response
+}
+
+async fn handle<F>(mut stream: TcpStream, respond: Arc<F>)
+where
+    F: Fn(&str) -> String,
+{
+    let mut buffer = Vec::new();
+
+    // Keep-alive connections carry one request after the other.
+    while let Some(request) = read_request(&mut stream, &mut buffer).await {
+        if stream
+            .write_all(respond(&request).as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn read_request(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<String> {
+    let mut chunk = [0; 4096];
+
+    loop {
+        if let Some(head_end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&buffer[..head_end]).to_lowercase();
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|length| length.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+
+            let end = head_end + 4 + length;
+            if buffer.len() >= end {
+                let request = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                buffer.drain(..end);
+                return Some(request);
+            }
+        }
+
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+}